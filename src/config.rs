@@ -0,0 +1,68 @@
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context as _, Result};
+use serde::Deserialize;
+
+use crate::{input, ui};
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Command line options keyed by their long names without the leading `--`.
+    #[serde(default)]
+    pub options: toml::Table,
+    /// Inputs opened in addition to those specified in the command line.
+    #[serde(default)]
+    pub inputs:  Vec<input::Spec>,
+    /// Display settings for series matching a label regex.
+    #[serde(default)]
+    pub series:  Vec<ui::SeriesRule>,
+    /// Key bindings overriding the defaults, keyed by scope and action names.
+    #[serde(default)]
+    pub keys:    ui::KeyConfig,
+}
+
+/// Returns `$XDG_CONFIG_HOME/lpl/config.toml`, falling back to `~/.config/lpl/config.toml`.
+pub fn default_path() -> Option<PathBuf> {
+    let config_home = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_home.join("lpl").join("config.toml"))
+}
+
+pub fn load(path: &Path) -> Result<Config> {
+    let content = std::fs::read_to_string(path).context("read config file")?;
+    toml::from_str(&content).context("parse config file")
+}
+
+impl Config {
+    /// Converts `options` into command line arguments,
+    /// to be inserted before the actual command line arguments so that the latter take precedence.
+    pub fn to_args(&self) -> Result<Vec<OsString>> {
+        let mut args = Vec::new();
+        for (key, value) in &self.options {
+            let values = match value {
+                toml::Value::Array(values) => values.as_slice(),
+                value => std::slice::from_ref(value),
+            };
+            for value in values {
+                let value = match value {
+                    toml::Value::Boolean(true) => {
+                        args.push(format!("--{key}").into());
+                        continue;
+                    }
+                    toml::Value::Boolean(false) => continue,
+                    toml::Value::String(value) => value.clone(),
+                    toml::Value::Integer(value) => value.to_string(),
+                    toml::Value::Float(value) => value.to_string(),
+                    _ => anyhow::bail!("unsupported value type for option {key:?}"),
+                };
+                args.push(format!("--{key}={value}").into());
+            }
+        }
+        Ok(args)
+    }
+}
@@ -1,41 +1,179 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::pin::Pin;
+use std::sync::atomic::{self, AtomicBool, AtomicUsize};
+use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 use std::{fmt, thread};
 
 use anyhow::{Context as _, Result};
 use arcstr::ArcStr;
 use futures::channel::mpsc;
-use futures::Future;
+use futures::{future, select, Future, FutureExt as _, SinkExt as _, StreamExt as _};
+use parking_lot::Mutex;
+use regex::Regex;
 use tokio::fs;
 use tokio_util::sync::CancellationToken;
 
+use self::follow::Follow;
+use self::labels::LabelRules;
+use self::long::LongFormat;
 use self::notifier::open_poll;
+pub use self::status::{InputStatus, State};
+use self::text::LineParser;
 
+mod command;
 mod csv;
+mod events;
+mod follow;
+mod glob;
 mod json;
+mod labels;
+mod long;
+mod text;
 
 mod notifier;
+mod status;
 
 #[derive(Debug, clap::Args)]
 #[group(id = "Inputs")]
+#[allow(clippy::struct_excessive_bools)] // command line flags
 pub struct Options {
     /// Read inputs from a CSV stream with an initial header line.
     #[clap(long)]
     pub csv:                Vec<PathBuf>,
-    /// Poll new changes from a CSV file periodically.
+    /// Poll new changes from a CSV file periodically, in the form `HEADER=PATH`.
     #[clap(long)]
     pub csv_poll:           Vec<String>,
-    /// Delimiter used in CSV files
-    #[clap(long, default_value_t = ',')]
+    /// Poll all CSV files matching a glob pattern, including files created later,
+    /// in the form `[HEADER=]PATTERN`.
+    ///
+    /// Without a header, each file has a single column labelled by the path components matched
+    /// by components with wildcards, e.g. `thermal_zone0` for `/sys/class/thermal/*/temp`.
+    /// With a header, the labels of its columns are prefixed by that label.
+    #[clap(long)]
+    pub csv_poll_glob:      Vec<String>,
+    /// Delimiter used in CSV files, where `tab` is accepted for TSV files.
+    #[clap(long, default_value_t = ',', value_parser = parse_delimiter)]
     pub csv_poll_delimiter: char,
+    /// Split CSV fields by runs of whitespace instead of a delimiter,
+    /// e.g. for the output of `vmstat` and `iostat`.
+    #[clap(long)]
+    pub csv_whitespace:     bool,
+    /// Treat `"` in CSV fields literally instead of as quotes.
+    #[clap(long)]
+    pub csv_no_quoting:     bool,
+    /// Ignore CSV lines starting with this character.
+    #[clap(long)]
+    pub csv_comment:        Option<char>,
+    /// Skip this many lines at the start of CSV streams and of each poll, before the header.
+    #[clap(long, default_value_t = 0)]
+    pub csv_skip_lines:     usize,
+    /// CSV streams have no header line, and their columns are named `col0`, `col1`, ...
+    #[clap(long)]
+    pub csv_no_header:      bool,
+    /// Names of the columns of CSV streams without a header line,
+    /// separated like the fields of the stream, e.g. `time,cpu,mem`.
+    #[clap(long, conflicts_with = "csv_no_header")]
+    pub csv_columns:        Option<String>,
 
     /// Read inputs from a JSON Lines stream.
     #[clap(long)]
-    pub json:      Vec<PathBuf>,
+    pub json:            Vec<PathBuf>,
     /// Poll new changes from a JSON file periodically.
     #[clap(long)]
-    pub json_poll: Vec<PathBuf>,
+    pub json_poll:       Vec<PathBuf>,
+    /// Poll all JSON files matching a glob pattern, including files created later.
+    ///
+    /// The labels from each file are prefixed by the path components matched by components with
+    /// wildcards.
+    #[clap(long)]
+    pub json_poll_glob:  Vec<PathBuf>,
+    /// Treat string values of this top-level JSON key as event annotations.
+    #[clap(long)]
+    pub json_annotation: Vec<String>,
+
+    /// Read a CSV stream in long format, with one observation per row.
+    #[clap(long, requires = "long_label")]
+    pub csv_long:   Vec<PathBuf>,
+    /// Read a JSON Lines stream in long format, with one observation per line.
+    #[clap(long, requires = "long_label")]
+    pub json_long:  Vec<PathBuf>,
+    /// Label the observations of `--csv-long` and `--json-long` inputs
+    /// by the values of these columns.
+    ///
+    /// The first column is the metric name and the others are tags,
+    /// e.g. `--long-label metric --long-label host` labels a row as `cpu{host=a}`.
+    #[clap(long)]
+    pub long_label: Vec<String>,
+    /// The column of the value in long format inputs.
+    #[clap(long, default_value = "value")]
+    pub long_value: String,
+    /// The column of the observation time in long format inputs,
+    /// as Unix seconds or an RFC 3339 timestamp.
+    ///
+    /// Defaults to the time the row is read.
+    #[clap(long)]
+    pub long_time:  Option<String>,
+
+    /// Read a stream of log lines, extracting the named capture groups of `--pattern` as series.
+    #[clap(long, requires = "pattern")]
+    pub regex:   Vec<PathBuf>,
+    /// The regex matched against each line of `--regex` inputs,
+    /// e.g. `latency=(?P<latency>[0-9.]+)ms`.
+    ///
+    /// Each named capture group with a numeric match is a series named by the group.
+    #[clap(long)]
+    pub pattern: Option<String>,
+    /// Read a stream of logfmt lines, where each numeric `key=value` pair is a series.
+    #[clap(long)]
+    pub logfmt:  Vec<PathBuf>,
+
+    /// Read event annotations from a stream, one event per line.
+    #[clap(long)]
+    pub events: Vec<PathBuf>,
+
+    /// Run a shell command and read JSON Lines from its standard output.
+    ///
+    /// The command is killed when its input is stopped or lpl exits.
+    #[clap(long)]
+    pub command: Vec<String>,
+
+    /// Open inputs with their own options, in the same syntax as the command line,
+    /// e.g. `--input '--csv vmstat.txt --csv-whitespace --csv-skip-lines 1'`.
+    ///
+    /// Options inside the group, such as the CSV format, only apply to the inputs in the group,
+    /// and options outside the group do not apply to them,
+    /// except `--json-annotation`, `--rename` and `--drop`, which apply to all inputs.
+    /// Arguments containing spaces can be quoted as in a shell.
+    #[clap(long, allow_hyphen_values = true)]
+    pub input:        Vec<String>,
+    /// Namespace the labels of series from the inputs as `PREFIX.LABEL`,
+    /// e.g. `--input '--label-prefix host1 --csv host1.csv'` plots `cpu` as `host1.cpu`.
+    ///
+    /// Applies to the inputs in the same `--input` group, or on the command line if outside groups.
+    #[clap(long, value_name = "PREFIX")]
+    pub label_prefix: Option<String>,
+
+    /// Rename series from all inputs, in the form `REGEX=REPLACEMENT`.
+    ///
+    /// Every match of `REGEX` in the series name, including its input prefix but not its tags,
+    /// is replaced by `REPLACEMENT`, which may refer to capture groups as `$1` or `${name}`.
+    /// Rules are applied in order, e.g. `--rename '^(.*)_bytes$=${1}_B'`.
+    #[clap(long, value_parser = labels::parse_rename_rule)]
+    pub rename: Vec<(Regex, String)>,
+    /// Drop series from all inputs with names matching this regex after renaming.
+    #[clap(long)]
+    pub drop:   Vec<Regex>,
+
+    /// Keep reading stream inputs from regular files as they grow, like `tail -F`.
+    ///
+    /// Files are reopened when they are truncated or replaced by log rotation.
+    #[clap(long)]
+    pub follow:          bool,
+    /// Start following files from their end, skipping their existing contents.
+    #[clap(long, requires = "follow")]
+    pub follow_from_end: bool,
 
     /// The frequency of polling files for *-poll inputs in seconds.
     #[arg(long, value_parser = |v: &str| v.parse::<f32>().map(Duration::from_secs_f32), default_value = "1")]
@@ -43,58 +181,429 @@ pub struct Options {
 }
 
 impl Options {
-    pub async fn open(&self, cancel: &CancellationToken) -> Result<Input> {
-        let (input_send, input_recv) = mpsc::channel(0);
-        let (warn_send, warn_recv) = mpsc::channel(16);
-        let warnings = WarningSender { prefix: ArcStr::default(), sender: warn_send };
+    /// Converts the inputs specified in command line arguments into input specifications.
+    fn specs(&self) -> Result<Vec<Spec>> {
+        let mut specs = Vec::new();
+
+        let paths = [
+            (Kind::Json, &self.json),
+            (Kind::JsonPoll, &self.json_poll),
+            (Kind::Csv, &self.csv),
+            (Kind::Logfmt, &self.logfmt),
+        ];
+        for (kind, paths) in paths {
+            specs.extend(paths.iter().map(|path| Spec::new(kind, path)));
+        }
+        let long = LongFormat {
+            labels: self.long_label.clone(),
+            value:  self.long_value.clone(),
+            time:   self.long_time.clone(),
+        };
+        for (kind, paths) in [(Kind::Csv, &self.csv_long), (Kind::Json, &self.json_long)] {
+            for path in paths {
+                specs.push(Spec { long: Some(long.clone()), ..Spec::new(kind, path) });
+            }
+        }
 
-        let mut workers = Vec::new();
+        specs.extend(self.events.iter().map(|path| Spec::new(Kind::Events, path)));
 
-        let watcher = notifier::start(warnings.with_prefix("inotify: "))?;
+        for arg in &self.csv_poll {
+            let (header, path) = arg.split_once('=').context(
+                "--csv-poll argument should be in the form `column1,column2,column3=path/to/csv`",
+            )?;
+            specs.push(Spec { header: Some(header.to_string()), ..Spec::new(Kind::CsvPoll, path) });
+        }
+        for arg in &self.csv_poll_glob {
+            let (header, pattern) = match arg.split_once('=') {
+                Some((header, pattern)) => (Some(header.to_string()), pattern),
+                None => (None, arg.as_str()),
+            };
+            specs.push(Spec { header, glob: true, ..Spec::new(Kind::CsvPoll, pattern) });
+        }
+        for pattern in &self.json_poll_glob {
+            specs.push(Spec { glob: true, ..Spec::new(Kind::JsonPoll, pattern) });
+        }
 
-        for path in &self.json {
-            let worker = json::open(path.clone(), &input_send)
-                .await
-                .with_context(|| format!("open {}", path.display()))?;
-            workers.push((path.clone(), worker));
+        for path in &self.regex {
+            specs.push(Spec { pattern: self.pattern.clone(), ..Spec::new(Kind::Regex, path) });
         }
 
-        for path in &self.json_poll {
-            let worker =
-                open_poll(path.clone(), self.poll_period, &watcher, &input_send, json::PollParser)?;
-            workers.push((path.clone(), worker));
+        for command in &self.command {
+            specs.push(Spec::new(Kind::Command, command));
         }
 
-        for path in &self.csv {
-            let worker = csv::open(path, &input_send, self.csv_poll_delimiter)
-                .await
-                .with_context(|| format!("open {}", path.display()))?;
-            workers.push((path.clone(), worker));
+        for spec in &mut specs {
+            spec.follow = self.follow;
+            spec.follow_from_end = self.follow_from_end;
+            spec.poll_period = Some(self.poll_period.as_secs_f32());
+            spec.label_prefix = self.label_prefix.as_ref().map(|prefix| format!("{prefix}."));
+
+            if matches!(spec.kind, Kind::Csv | Kind::CsvPoll) {
+                spec.delimiter = Some(self.csv_poll_delimiter);
+                spec.whitespace = self.csv_whitespace;
+                spec.no_quoting = self.csv_no_quoting;
+                spec.comment = self.csv_comment;
+                spec.skip_lines = self.csv_skip_lines;
+            }
+            if let Kind::Csv = spec.kind {
+                spec.no_header = self.csv_no_header;
+                spec.header.clone_from(&self.csv_columns);
+            }
         }
 
-        for arg in &self.csv_poll {
-            let (path, parser) = csv::Parser::new(arg, self.csv_poll_delimiter)?;
-            let worker =
-                open_poll(path.to_path_buf(), self.poll_period, &watcher, &input_send, parser)?;
-            workers.push((path.to_path_buf(), worker));
+        for group in &self.input {
+            let specs_in_group = shell_words::split(group)
+                .map_err(anyhow::Error::from)
+                .and_then(|args| parse_specs(&args))
+                .with_context(|| format!("parse --input {group:?}"))?;
+            specs.extend(specs_in_group);
         }
 
-        for (path, worker) in workers {
-            let mut warn_send = warnings.with_prefix(&format!("{}: ", path.display()));
+        Ok(specs)
+    }
+
+    /// Opens the inputs specified in command line arguments followed by `extra_specs`.
+    pub async fn open(&self, extra_specs: &[Spec], cancel: &CancellationToken) -> Result<Input> {
+        let (input_send, input_recv) = mpsc::channel(0);
+        let (warn_send, warn_recv) = mpsc::channel(16);
+        let warnings = WarningSender { prefix: ArcStr::default(), sender: warn_send, status: None };
+
+        let opener = Opener {
+            poll_period:     self.poll_period,
+            delimiter:       self.csv_poll_delimiter,
+            annotation_keys: self.json_annotation.clone().into(),
+            labels:          Arc::new(LabelRules::new(self.rename.clone(), self.drop.clone())),
+            watcher:         notifier::start(warnings.with_prefix("inotify: "))?,
+            messages:        input_send,
+            active:          Arc::default(),
+            exit_on_end:     Arc::new(AtomicBool::new(true)),
+            statuses:        Arc::default(),
+            warnings:        warnings.clone(),
+            cancel:          cancel.clone(),
+        };
+
+        // hold the message stream open until all inputs are opened,
+        // in case an input ends before the next one is opened
+        opener.active.fetch_add(1, atomic::Ordering::SeqCst);
+        for spec in self.specs()?.iter().chain(extra_specs) {
+            opener.open_with(spec, opener.cancel.child_token()).await?;
+        }
+        opener.end_input();
+
+        Ok(Input { messages: input_recv, warnings: warn_recv, warning_sender: warnings, opener })
+    }
+}
+
+fn parse_delimiter(arg: &str) -> Result<char> {
+    if arg == "tab" {
+        return Ok('\t');
+    }
+    let mut chars = arg.chars();
+    match (chars.next(), chars.next()) {
+        (Some(delimiter), None) => Ok(delimiter),
+        _ => anyhow::bail!("delimiter should be a single character or `tab`"),
+    }
+}
+
+/// Parses input arguments in the same syntax as the command line, e.g. `--json PATH`.
+///
+/// Options that apply to all inputs, such as `--rename`, are rejected.
+pub fn parse_specs(args: &[String]) -> Result<Vec<Spec>> {
+    #[derive(clap::Parser)]
+    #[command(no_binary_name = true)]
+    struct Args {
+        #[command(flatten)]
+        inputs: Options,
+    }
+
+    let args = <Args as clap::Parser>::try_parse_from(args)?;
+    let inputs = &args.inputs;
+    for (option, is_set) in [
+        ("--json-annotation", !inputs.json_annotation.is_empty()),
+        ("--rename", !inputs.rename.is_empty()),
+        ("--drop", !inputs.drop.is_empty()),
+    ] {
+        anyhow::ensure!(
+            !is_set,
+            "{option} applies to all inputs and cannot be set for some inputs"
+        );
+    }
+    inputs.specs()
+}
+
+/// The input reading JSON Lines from a shell command, which may contain spaces unlike
+/// [`parse_specs`].
+pub fn command_spec(command: &str) -> Spec { Spec::new(Kind::Command, command) }
+
+/// Opens inputs and forwards their messages, both at startup and at runtime.
+#[derive(Clone)]
+pub struct Opener {
+    poll_period:     Duration,
+    delimiter:       char,
+    annotation_keys: Arc<[String]>,
+    labels:          Arc<LabelRules>,
+    watcher:         notifier::Notifier<notify::RecommendedWatcher>,
+    messages:        mpsc::Sender<Message>,
+    /// Number of inputs that have not reached the end yet.
+    active:          Arc<AtomicUsize>,
+    /// Whether the message stream ends, which exits lpl, once all inputs have ended.
+    ///
+    /// Cleared once inputs are stopped or added at runtime,
+    /// so that the data is kept until the user quits.
+    exit_on_end:     Arc<AtomicBool>,
+    /// Every input opened so far, including those that have ended.
+    statuses:        Arc<Mutex<Vec<Arc<InputStatus>>>>,
+    warnings:        WarningSender,
+    cancel:          CancellationToken,
+}
+
+impl Opener {
+    /// Opens an input at runtime and spawns a worker that forwards its messages.
+    pub async fn open(&self, spec: &Spec) -> Result<()> {
+        self.exit_on_end.store(false, atomic::Ordering::SeqCst);
+        self.open_with(spec, self.cancel.child_token()).await
+    }
+
+    /// Stops an input at runtime, along with the inputs it opened.
+    pub fn stop(&self, status: &InputStatus) {
+        self.exit_on_end.store(false, atomic::Ordering::SeqCst);
+        status.stop();
+    }
 
-            let worker = worker(warn_send.clone(), cancel.clone());
-            tokio::spawn(async move {
-                if let Err(err) = worker.await {
-                    warn_send.send(format!("Error: {err}"));
+    /// Opens the input with a worker that stops when `cancel` is cancelled.
+    async fn open_with(&self, spec: &Spec, cancel: CancellationToken) -> Result<()> {
+        // each worker sends to its own channel so that per-input settings can be applied
+        let (worker_send, worker_recv) = mpsc::channel(0);
+        let worker = self
+            .open_worker(spec, &worker_send)
+            .await
+            .with_context(|| format!("open {}", spec.path.display()))?;
+
+        let status = Arc::new(InputStatus::new(spec, cancel.clone()));
+        self.statuses.lock().push(status.clone());
+
+        let mut warn_send = self
+            .warnings
+            .with_prefix(&format!("{}: ", spec.path.display()))
+            .with_status(status.clone());
+        let worker = worker(warn_send.clone(), cancel.clone());
+        let worker_cancel = cancel.clone();
+        tokio::spawn(async move {
+            // stream workers only check for cancellation between lines
+            let result = select! {
+                result = worker.fuse() => result,
+                () = worker_cancel.cancelled().fuse() => Ok(()),
+            };
+            let state = match &result {
+                Err(_) => State::Errored,
+                Ok(()) if worker_cancel.is_cancelled() => State::Stopped,
+                Ok(()) => State::Eof,
+            };
+            if let Err(err) = result {
+                warn_send.error(format!("Error: {err}"));
+            }
+            warn_send.set_state(state);
+        });
+
+        let label_prefix = spec.label_prefix.clone().unwrap_or_default();
+        let labels = self.labels.clone();
+        let mut input_send = self.messages.clone();
+        let opener = self.clone();
+        self.active.fetch_add(1, atomic::Ordering::SeqCst);
+        tokio::spawn(async move {
+            let mut messages = worker_recv.filter_map(|mut message: Message| {
+                let keep = !status.is_paused() && {
+                    message.key.name.insert_str(0, &label_prefix);
+                    labels.apply(&mut message.key.name)
+                };
+                if keep {
+                    status.record_message(&message.key);
                 }
+                future::ready(keep.then_some(Ok(message)))
             });
+            _ = input_send.send_all(&mut messages).await;
+
+            opener.end_input();
+        });
+
+        Ok(())
+    }
+
+    /// Ends the message stream once all inputs have ended, unless disabled by `exit_on_end`.
+    fn end_input(&self) {
+        if self.active.fetch_sub(1, atomic::Ordering::SeqCst) == 1
+            && self.exit_on_end.load(atomic::Ordering::SeqCst)
+        {
+            self.messages.clone().close_channel();
+        }
+    }
+
+    /// The statuses of every input opened so far, in the order they were opened.
+    pub fn statuses(&self) -> Vec<Arc<InputStatus>> { self.statuses.lock().clone() }
+
+    async fn open_worker(
+        &self,
+        spec: &Spec,
+        send: &mpsc::Sender<Message>,
+    ) -> Result<WorkerBuilder> {
+        let path = spec.path.clone();
+        let poll_period = spec.poll_period.map_or(self.poll_period, Duration::from_secs_f32);
+        if spec.glob {
+            anyhow::ensure!(
+                matches!(spec.kind, Kind::CsvPoll | Kind::JsonPoll),
+                "glob patterns are only supported for polling inputs"
+            );
+            return glob::open(self, spec, poll_period, &self.watcher, send);
+        }
+
+        let long = spec.long.clone();
+        if let Kind::Command = spec.kind {
+            let command = path.to_str().context("command must be valid UTF-8")?;
+            return command::open(command, send, self.annotation_keys.clone(), long);
+        }
+
+        // pipes and other non-regular files are streams that need no following
+        let follow = if spec.follow && std::fs::metadata(&path).is_ok_and(|meta| meta.is_file()) {
+            Some(Follow::new(path.clone(), &self.watcher, poll_period, spec.follow_from_end)?)
+        } else {
+            None
+        };
+
+        match spec.kind {
+            Kind::Json => json::open(path, send, self.annotation_keys.clone(), long, follow).await,
+            Kind::JsonPoll => {
+                let parser =
+                    json::PollParser { annotation_keys: self.annotation_keys.clone(), long };
+                open_poll(path, poll_period, &self.watcher, send, parser)
+            }
+            Kind::Events => events::open(&path, send, follow).await,
+            Kind::Csv => {
+                let dialect = csv::Dialect::new(spec, self.delimiter)?;
+                csv::open(&path, send, dialect, spec.csv_columns(), long, follow).await
+            }
+            Kind::CsvPoll => {
+                let columns = match spec.csv_columns() {
+                    csv::Columns::HeaderLine => {
+                        anyhow::bail!("csv-poll inputs require a header or no-header")
+                    }
+                    columns => columns,
+                };
+                let parser =
+                    csv::Parser::new(columns, csv::Dialect::new(spec, self.delimiter)?, long)?;
+                open_poll(path, poll_period, &self.watcher, send, parser)
+            }
+            Kind::Regex => {
+                let pattern = spec.pattern.as_deref().context("regex inputs require a pattern")?;
+                text::open(&path, send, LineParser::pattern(pattern)?, follow).await
+            }
+            Kind::Logfmt => text::open(&path, send, LineParser::Logfmt, follow).await,
+            Kind::Command => unreachable!("commands are not read from files"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Kind {
+    Csv,
+    CsvPoll,
+    Json,
+    JsonPoll,
+    Events,
+    Regex,
+    Logfmt,
+    Command,
+}
+
+impl Kind {
+    /// The name of the kind in the configuration file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::CsvPoll => "csv-poll",
+            Self::Json => "json",
+            Self::JsonPoll => "json-poll",
+            Self::Events => "events",
+            Self::Regex => "regex",
+            Self::Logfmt => "logfmt",
+            Self::Command => "command",
         }
+    }
+}
 
-        Ok(Input {
-            messages:       input_recv,
-            warnings:       warn_recv,
-            warning_sender: warnings,
-        })
+/// Specifies an input source along with its per-input settings.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+#[allow(clippy::struct_excessive_bools)] // configuration file flags
+pub struct Spec {
+    pub kind:            Kind,
+    /// The file to read, or the shell command to run for command inputs.
+    pub path:            PathBuf,
+    /// The CSV header for polling CSV inputs and CSV streams without a header line.
+    pub header:          Option<String>,
+    /// CSV inputs have no header, and their columns are named `col0`, `col1`, ...
+    #[serde(default)]
+    pub no_header:       bool,
+    /// Overrides `--csv-poll-delimiter` for CSV inputs.
+    pub delimiter:       Option<char>,
+    /// Splits CSV fields by runs of whitespace instead of the delimiter.
+    #[serde(default)]
+    pub whitespace:      bool,
+    /// Treats quotes in CSV fields literally.
+    #[serde(default)]
+    pub no_quoting:      bool,
+    /// Ignores CSV lines starting with this character.
+    pub comment:         Option<char>,
+    /// Skips this many lines at the start of CSV streams and of each poll.
+    #[serde(default)]
+    pub skip_lines:      usize,
+    /// Overrides `--poll-period` in seconds for polling inputs.
+    pub poll_period:     Option<f32>,
+    /// Prepended to the labels of all series from this input.
+    pub label_prefix:    Option<String>,
+    /// Reads CSV or JSON inputs in long format.
+    pub long:            Option<LongFormat>,
+    /// The regex with named capture groups for regex inputs.
+    pub pattern:         Option<String>,
+    /// Treats the path as a glob pattern of polling inputs, opening each matching file.
+    #[serde(default)]
+    pub glob:            bool,
+    /// Keeps reading stream inputs from regular files as they grow.
+    #[serde(default)]
+    pub follow:          bool,
+    /// Starts following files from their end.
+    #[serde(default)]
+    pub follow_from_end: bool,
+}
+
+impl Spec {
+    fn new(kind: Kind, path: impl Into<PathBuf>) -> Self {
+        Self {
+            kind,
+            path: path.into(),
+            header: None,
+            no_header: false,
+            delimiter: None,
+            whitespace: false,
+            no_quoting: false,
+            comment: None,
+            skip_lines: 0,
+            poll_period: None,
+            label_prefix: None,
+            long: None,
+            pattern: None,
+            glob: false,
+            follow: false,
+            follow_from_end: false,
+        }
+    }
+
+    fn csv_columns(&self) -> csv::Columns<'_> {
+        match (&self.header, self.no_header) {
+            (Some(header), _) => csv::Columns::Header(header),
+            (None, true) => csv::Columns::Numbered,
+            (None, false) => csv::Columns::HeaderLine,
+        }
     }
 }
 
@@ -102,6 +611,8 @@ impl Options {
 pub struct WarningSender {
     prefix: ArcStr,
     sender: mpsc::Sender<(SystemTime, String)>,
+    /// The input that the warnings are about, which records the last warning as its error.
+    status: Option<Arc<InputStatus>>,
 }
 
 impl WarningSender {
@@ -109,8 +620,28 @@ impl WarningSender {
         let _ = self.sender.try_send((SystemTime::now(), format!("{}{message}", &self.prefix)));
     }
 
+    /// Sends a warning about an error, which is also recorded as the last error of the input.
+    pub fn error(&mut self, message: impl fmt::Display) {
+        let message = message.to_string();
+        if let Some(status) = &self.status {
+            status.record_error(message.clone());
+        }
+        self.send(message);
+    }
+
     pub fn with_prefix(&self, prefix: &str) -> Self {
-        Self { prefix: arcstr::format!("{prefix}{}", &self.prefix), sender: self.sender.clone() }
+        Self { prefix: arcstr::format!("{prefix}{}", &self.prefix), ..self.clone() }
+    }
+
+    fn with_status(&self, status: Arc<InputStatus>) -> Self {
+        Self { status: Some(status), ..self.clone() }
+    }
+
+    /// Updates the state of the input that the warnings are about, if any.
+    pub fn set_state(&self, state: State) {
+        if let Some(status) = &self.status {
+            status.set_state(state);
+        }
     }
 }
 
@@ -118,28 +649,123 @@ pub struct Input {
     pub messages:       mpsc::Receiver<Message>,
     pub warnings:       mpsc::Receiver<(SystemTime, String)>,
     pub warning_sender: WarningSender,
+    pub opener:         Opener,
 }
 
 #[derive(Debug)]
 pub struct Message {
-    pub label: String,
-    pub value: f64,
+    pub key:   SeriesKey,
+    pub value: Value,
     pub time:  SystemTime,
 }
 
+/// Identifies a series by a metric name and a set of key/value tags.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SeriesKey {
+    pub name: String,
+    pub tags: BTreeMap<String, String>,
+}
+
+impl SeriesKey {
+    pub fn new(name: String) -> Self { Self { name, tags: BTreeMap::new() } }
+
+    /// Parses a label in the form `name{key=value,...}` like the Prometheus exposition format,
+    /// where values may be quoted with `"`.
+    ///
+    /// Labels not in this form are treated as a name without tags.
+    pub fn parse(label: &str) -> Self {
+        parse_tagged(label).unwrap_or_else(|| Self::new(label.to_string()))
+    }
+
+    /// Formats the key as `name{key=value,...}`, or `name` if there are no tags.
+    ///
+    /// This is the inverse of [`SeriesKey::parse`].
+    pub fn label(&self) -> String {
+        if self.tags.is_empty() {
+            return self.name.clone();
+        }
+
+        let tags: Vec<_> = self.tags.iter().map(|(key, value)| format_tag(key, value)).collect();
+        format!("{}{{{}}}", self.name, tags.join(","))
+    }
+}
+
+/// Formats a tag as `key=value`, quoting the value if it contains special characters.
+pub fn format_tag(key: &str, value: &str) -> String {
+    if value.is_empty()
+        || value.contains(|char: char| ",{}=\"\\".contains(char) || char.is_whitespace())
+    {
+        let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+        format!("{key}=\"{escaped}\"")
+    } else {
+        format!("{key}={value}")
+    }
+}
+
+fn parse_tagged(label: &str) -> Option<SeriesKey> {
+    let (name, body) = label.strip_suffix('}')?.split_once('{')?;
+    let mut tags = BTreeMap::new();
+    let mut rest = body.trim_start();
+    while !rest.is_empty() {
+        let (key, after_key) = rest.split_once('=')?;
+        let after_key = after_key.trim_start();
+
+        let (value, after_value) = if let Some(quoted) = after_key.strip_prefix('"') {
+            let mut value = String::new();
+            let mut chars = quoted.char_indices();
+            let end = loop {
+                match chars.next()? {
+                    (index, '"') => break index,
+                    (_, '\\') => value.push(chars.next()?.1),
+                    (_, char) => value.push(char),
+                }
+            };
+            (value, &quoted[end + 1..])
+        } else {
+            let end = after_key.find(',').unwrap_or(after_key.len());
+            (after_key[..end].trim_end().to_string(), &after_key[end..])
+        };
+        tags.insert(key.trim().to_string(), value);
+
+        let after_value = after_value.trim_start();
+        rest = match after_value.strip_prefix(',') {
+            Some(next) => next.trim_start(),
+            None if after_value.is_empty() => after_value,
+            None => return None,
+        };
+    }
+    Some(SeriesKey { name: name.to_string(), tags })
+}
+
+#[derive(Debug)]
+pub enum Value {
+    Number(f64),
+    /// A non-numeric event, drawn as a labeled marker on the time axis.
+    Annotation(String),
+}
+
 type WorkerBuilder = Box<dyn FnOnce(WarningSender, CancellationToken) -> Worker>;
 type Worker = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
 
 /// Workaround for tokio workers unable to perform non-blocking reads on non-regular files.
+///
+/// If `follow` is set, the file is read like `tail -F` instead of stopping at its end.
 async fn thread_line_reader(
     tokio_file: fs::File,
+    follow: Option<Follow>,
     cancel: CancellationToken,
     mut warn_send: WarningSender,
 ) -> tokio::sync::mpsc::Receiver<(String, SystemTime)> {
     let (send, recv) = tokio::sync::mpsc::channel(1);
 
     let std_file = tokio_file.into_std().await;
+    let runtime = tokio::runtime::Handle::current();
     thread::spawn(move || {
+        if let Some(follow) = follow {
+            follow.read_lines(std_file, &runtime, &cancel, &send, &mut warn_send);
+            return;
+        }
+
         let mut buf = std::io::BufReader::new(std_file);
         while !cancel.is_cancelled() {
             use std::io::BufRead as _;
@@ -148,10 +774,63 @@ async fn thread_line_reader(
             match buf.read_line(&mut line) {
                 Ok(0) => break,
                 Ok(_) => drop(send.blocking_send((line, SystemTime::now()))),
-                Err(err) => warn_send.send(format!("{err:?}")),
+                Err(err) => warn_send.error(format!("{err:?}")),
             }
         }
     });
 
     recv
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{format_tag, SeriesKey};
+
+    fn key(name: &str, tags: &[(&str, &str)]) -> SeriesKey {
+        SeriesKey {
+            name: name.to_string(),
+            tags: tags.iter().map(|&(key, value)| (key.to_string(), value.to_string())).collect(),
+        }
+    }
+
+    #[test]
+    fn parse_tags() {
+        assert_eq!(
+            SeriesKey::parse("requests{status=200,host=a}"),
+            key("requests", &[("host", "a"), ("status", "200")]),
+        );
+        assert_eq!(
+            SeriesKey::parse("requests{ host = a , path=\"/a,b\" }"),
+            key("requests", &[("host", "a"), ("path", "/a,b")]),
+        );
+        assert_eq!(
+            SeriesKey::parse(r#"m{msg="say \"hi\"",dir="C:\\"}"#),
+            key("m", &[("dir", "C:\\"), ("msg", "say \"hi\"")]),
+        );
+        assert_eq!(SeriesKey::parse("m{}"), key("m", &[]));
+    }
+
+    #[test]
+    fn parse_untagged() {
+        assert_eq!(SeriesKey::parse("cpu.user"), key("cpu.user", &[]));
+        for label in ["a{b}", "a{b=1", "a{b=\"1}", "a{b=\"1\"c}", "a{b=1}c"] {
+            assert_eq!(SeriesKey::parse(label), key(label, &[]), "{label}");
+        }
+    }
+
+    #[test]
+    fn format_tags() {
+        assert_eq!(format_tag("host", "a"), "host=a");
+        assert_eq!(format_tag("host", ""), "host=\"\"");
+        assert_eq!(format_tag("path", "/a,b"), "path=\"/a,b\"");
+        assert_eq!(format_tag("msg", "say \"hi\""), r#"msg="say \"hi\"""#);
+        assert_eq!(format_tag("dir", "C:\\"), r#"dir="C:\\""#);
+    }
+
+    #[test]
+    fn label_round_trip() {
+        let key = key("requests", &[("status", "200"), ("path", "/a, b"), ("msg", "\"x\\")]);
+        assert_eq!(key.label(), r#"requests{msg="\"x\\",path="/a, b",status=200}"#);
+        assert_eq!(SeriesKey::parse(&key.label()), key);
+    }
+}
@@ -0,0 +1,75 @@
+use std::io::{BufRead as _, BufReader};
+use std::process::{self, Stdio};
+use std::sync::Arc;
+use std::thread;
+
+use anyhow::{Context as _, Result};
+use futures::channel::mpsc;
+use tokio::fs;
+
+use super::long::LongFormat;
+use super::{json, Message, WorkerBuilder};
+
+/// Runs a shell command and reads JSON Lines from its standard output.
+///
+/// Lines written to standard error are reported as warnings.
+pub fn open(
+    command: &str,
+    send: &mpsc::Sender<Message>,
+    annotation_keys: Arc<[String]>,
+    long: Option<LongFormat>,
+) -> Result<WorkerBuilder> {
+    let mut child = shell(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("start command")?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let child = KillOnDrop(child);
+
+    let stdout = fs::File::from_std(std::fs::File::from(std::os::fd::OwnedFd::from(stdout)));
+    let reader = json::stream(stdout, send, annotation_keys, long, None);
+
+    Ok(Box::new(move |warnings, cancel| {
+        let mut stderr_warnings = warnings.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                stderr_warnings.send(line);
+            }
+        });
+        let reader = reader(warnings, cancel);
+
+        Box::pin(async move {
+            // the command is killed if the worker is dropped before it exits, e.g. when stopped
+            reader.await?;
+
+            let status = tokio::task::spawn_blocking(move || {
+                let mut child = child;
+                child.0.wait()
+            })
+            .await?
+            .context("wait for command")?;
+            anyhow::ensure!(status.success(), "command exited with {status}");
+            Ok(())
+        })
+    }))
+}
+
+fn shell(command: &str) -> process::Command {
+    let mut shell = process::Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+struct KillOnDrop(process::Child);
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        // reap the process after killing it, which fails if it has already exited
+        if self.0.kill().is_ok() {
+            _ = self.0.wait();
+        }
+    }
+}
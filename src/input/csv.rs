@@ -1,70 +1,133 @@
+use std::collections::HashSet;
+use std::io::Read as _;
 use std::path::Path;
 use std::time::SystemTime;
-use std::{iter, mem};
 
 use anyhow::{Context as _, Result};
 use futures::channel::mpsc;
 use futures::SinkExt;
 use tokio::fs;
-use tokio::io::{self, AsyncBufReadExt as _};
 
+use super::follow::Follow;
+use super::long::LongFormat;
 use super::notifier::FieldParser;
-use super::{Message, WorkerBuilder};
-
-fn parse_line(line: &[u8], delimiter: Delimiter) -> Result<Vec<String>> {
-    let mut records = csv::ReaderBuilder::new()
-        .has_headers(false)
-        .delimiter(delimiter.0)
-        .from_reader(line)
-        .into_records();
-    match records.next() {
-        Some(record) => {
-            Ok(record.context("CSV parse error")?.into_iter().map(str::to_string).collect())
+use super::{Message, SeriesKey, Spec, Value, WorkerBuilder};
+
+/// How the lines of a CSV input are split into fields.
+#[derive(Clone)]
+pub struct Dialect {
+    delimiter:  u8,
+    /// Fields are separated by runs of whitespace, ignoring leading whitespace,
+    /// like the output of `vmstat` and `iostat`.
+    whitespace: bool,
+    quoting:    bool,
+    /// Lines starting with this character are ignored.
+    comment:    Option<char>,
+    /// The number of lines skipped at the start of a stream or of each poll, before the header.
+    skip_lines: usize,
+}
+
+impl Dialect {
+    pub fn new(spec: &Spec, default_delimiter: char) -> Result<Self> {
+        let delimiter = spec.delimiter.unwrap_or(default_delimiter);
+        anyhow::ensure!(delimiter.is_ascii(), "CSV delimiter must be a single ASCII character");
+        Ok(Self {
+            delimiter:  delimiter as u8,
+            whitespace: spec.whitespace,
+            quoting:    !spec.no_quoting,
+            comment:    spec.comment,
+            skip_lines: spec.skip_lines,
+        })
+    }
+
+    fn parse_line(&self, line: &[u8]) -> Result<Vec<String>> {
+        if self.whitespace {
+            let line = String::from_utf8_lossy(line);
+            return Ok(line.split_whitespace().map(str::to_string).collect());
         }
-        None => Ok(Vec::new()),
+
+        let mut records = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .delimiter(self.delimiter)
+            .quoting(self.quoting)
+            .from_reader(line)
+            .into_records();
+        match records.next() {
+            Some(record) => {
+                Ok(record.context("CSV parse error")?.into_iter().map(str::to_string).collect())
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Whether the line is blank or a comment.
+    fn is_ignored(&self, line: &str) -> bool {
+        line.trim().is_empty() || self.comment.is_some_and(|comment| line.starts_with(comment))
     }
 }
 
+/// Where the column names of a CSV input come from.
 #[derive(Clone, Copy)]
-struct Delimiter(u8);
-
-impl Delimiter {
-    fn new(delimiter: char) -> Result<Self> {
-        anyhow::ensure!(
-            delimiter.is_ascii(),
-            "--csv-poll-delimiter must be a single ASCII character"
-        );
-        Ok(Self(delimiter as u8))
-    }
+pub enum Columns<'a> {
+    /// The first line that is not skipped or ignored.
+    HeaderLine,
+    /// A header supplied in place of a header line.
+    Header(&'a str),
+    /// `col0`, `col1`, ... for inputs without a header.
+    Numbered,
 }
 
 pub async fn open(
     path: &Path,
     send: &mpsc::Sender<Message>,
-    delimiter: char,
+    dialect: Dialect,
+    columns: Columns<'_>,
+    long: Option<LongFormat>,
+    follow: Option<Follow>,
 ) -> Result<WorkerBuilder> {
-    let delimiter = Delimiter::new(delimiter)?;
+    let has_header_line = matches!(columns, Columns::HeaderLine);
+    let skip_lines = dialect.skip_lines;
 
-    let mut fd = fs::File::open(path).await.context("cannot open file for reading")?;
+    // read unbuffered so that no data lines are consumed before the line reader starts
+    let path = path.to_path_buf();
+    let header_dialect = dialect.clone();
+    let (fd, header) = tokio::task::spawn_blocking(move || {
+        let mut fd = std::fs::File::open(path).context("cannot open file for reading")?;
+        for _ in 0..header_dialect.skip_lines {
+            read_line(&mut fd).context("read skipped line")?;
+        }
+        let mut header = Vec::new();
+        if has_header_line {
+            while let Some(line) = read_line(&mut fd).context("read header line")? {
+                if !header_dialect.is_ignored(&String::from_utf8_lossy(&line)) {
+                    header = line;
+                    break;
+                }
+            }
+        }
+        anyhow::Ok((fs::File::from_std(fd), header))
+    })
+    .await??;
 
-    let labels = {
-        let mut read = io::BufReader::new(&mut fd);
-        let mut line = String::new();
-        read.read_line(&mut line).await.context("read header line")?;
-        parse_line(line.as_bytes(), delimiter).context("parse header line")?
+    let parser = match columns {
+        Columns::HeaderLine => {
+            let header = dialect.parse_line(&header).context("parse header line")?;
+            Parser::from_header(Some(header), dialect, long)
+        }
+        columns => Parser::new(columns, dialect, long)?,
     };
 
     let mut send = send.clone();
 
-    let parser = Parser { labels, delimiter };
-
     Ok(Box::new(move |mut warnings, cancel| {
         Box::pin(async move {
-            let mut read = super::thread_line_reader(fd, cancel, warnings.clone()).await;
+            let follow = follow
+                .map(|follow| follow.with_skipped_lines(skip_lines + usize::from(has_header_line)));
+            let mut read = super::thread_line_reader(fd, follow, cancel, warnings.clone()).await;
 
             while let Some((line, time)) = read.recv().await {
-                if let Err(err) = parser.send_fields(time, &line, &mut send, |_| true).await {
-                    warnings.send(format!("Error: {err:?}"));
+                if let Err(err) = parser.send_line(time, &line, &mut send).await {
+                    warnings.error(format!("Error: {err:?}"));
                 }
             }
 
@@ -73,38 +136,114 @@ pub async fn open(
     }))
 }
 
+/// Reads a line without buffering, returning `None` at the end of the file.
+fn read_line(fd: &mut std::fs::File) -> Result<Option<Vec<u8>>> {
+    let mut line = Vec::new();
+    let mut byte = [0];
+    loop {
+        if fd.read(&mut byte)? == 0 {
+            return Ok((!line.is_empty()).then_some(line));
+        }
+        if byte[0] == b'\n' {
+            return Ok(Some(line));
+        }
+        line.push(byte[0]);
+    }
+}
+
 pub struct Parser {
-    labels:    Vec<String>,
-    delimiter: Delimiter,
+    /// `None` if the columns are numbered.
+    header:  Option<Vec<String>>,
+    labels:  Vec<SeriesKey>,
+    dialect: Dialect,
+    long:    Option<LongFormat>,
 }
 
 impl Parser {
-    pub fn new(arg: &str, delimiter: char) -> Result<(&Path, Self)> {
-        let delimiter = Delimiter::new(delimiter)?;
-
-        let (header, path) = arg.split_once('=').context(
-            "--csv-poll argument should be in the form `column1,column2,column3=path/to/csv`",
-        )?;
-        let labels = parse_line(header.as_bytes(), delimiter)?;
-        Ok((Path::new(path), Parser { labels, delimiter }))
+    pub fn new(columns: Columns, dialect: Dialect, long: Option<LongFormat>) -> Result<Self> {
+        let header = match columns {
+            Columns::Header(header) => Some(dialect.parse_line(header.as_bytes())?),
+            Columns::Numbered => None,
+            Columns::HeaderLine => anyhow::bail!("polling CSV inputs require a header"),
+        };
+        Ok(Self::from_header(header, dialect, long))
     }
 
-    async fn send_fields(
+    fn from_header(
+        header: Option<Vec<String>>,
+        dialect: Dialect,
+        long: Option<LongFormat>,
+    ) -> Self {
+        let labels = header.iter().flatten().map(|label| SeriesKey::parse(label)).collect();
+        Parser { header, labels, dialect, long }
+    }
+
+    fn label(&self, column: usize) -> Option<SeriesKey> {
+        match &self.header {
+            Some(_) => self.labels.get(column).cloned(),
+            None => Some(SeriesKey::new(format!("col{column}"))),
+        }
+    }
+
+    fn column_index(&self, name: &str) -> Option<usize> {
+        match &self.header {
+            Some(header) => header.iter().position(|column| column == name),
+            None => name.strip_prefix("col")?.parse().ok(),
+        }
+    }
+
+    /// Splits a line into fields,
+    /// returning `None` for blank lines, comments and headers repeated in the input.
+    fn fields(&self, line: &str) -> Result<Option<Vec<String>>> {
+        if self.dialect.is_ignored(line) {
+            return Ok(None);
+        }
+        let fields = self.dialect.parse_line(line.as_bytes())?;
+        Ok((self.header.as_ref() != Some(&fields)).then_some(fields))
+    }
+
+    /// Sends the values in a line of a streaming input.
+    async fn send_line(
         &self,
         time: SystemTime,
         line: &str,
         send: &mut mpsc::Sender<Message>,
-        mut admit: impl FnMut(usize) -> bool,
     ) -> Result<()> {
-        if line.is_empty() {
-            return Ok(());
+        match &self.long {
+            Some(long) => {
+                if let Some(message) = self.long_message(long, time, line)? {
+                    send.send(message).await?;
+                }
+                Ok(())
+            }
+            None => self.send_fields(time, line, send, |_| true).await,
         }
+    }
 
-        let line = parse_line(line.as_bytes(), self.delimiter)?;
-        for (column_id, (label, value)) in iter::zip(&self.labels, line).enumerate() {
+    /// Converts a line of a long format input into a message.
+    fn long_message(
+        &self,
+        long: &LongFormat,
+        time: SystemTime,
+        line: &str,
+    ) -> Result<Option<Message>> {
+        let Some(fields) = self.fields(line)? else { return Ok(None) };
+        long.message(|column| fields.get(self.column_index(column)?).cloned(), time)
+    }
+
+    async fn send_fields(
+        &self,
+        time: SystemTime,
+        line: &str,
+        send: &mut mpsc::Sender<Message>,
+        mut admit: impl FnMut(usize) -> bool,
+    ) -> Result<()> {
+        let Some(fields) = self.fields(line)? else { return Ok(()) };
+        for (column_id, value) in fields.into_iter().enumerate() {
+            let Some(label) = self.label(column_id) else { break };
             if let Ok(value) = value.parse() {
                 if admit(column_id) {
-                    send.feed(Message { label: label.clone(), value, time }).await?;
+                    send.feed(Message { key: label, value: Value::Number(value), time }).await?;
                 }
             }
         }
@@ -120,13 +259,25 @@ impl FieldParser for Parser {
         content: &str,
         send: &mut mpsc::Sender<Message>,
     ) -> Result<()> {
-        let mut dedup = vec![false; self.labels.len()];
-        for line in content.lines() {
-            self.send_fields(time, line, send, |column_id| {
-                let existed = mem::replace(&mut dedup[column_id], true);
-                !existed
-            })
-            .await?;
+        let lines = content.lines().skip(self.dialect.skip_lines);
+
+        if let Some(long) = &self.long {
+            // like wide format, only the first value of each series in a poll is used
+            let mut seen = HashSet::new();
+            for line in lines {
+                if let Some(message) = self.long_message(long, time, line)? {
+                    if seen.insert(message.key.label()) {
+                        send.feed(message).await?;
+                    }
+                }
+            }
+            send.flush().await?;
+            return Ok(());
+        }
+
+        let mut seen = HashSet::new();
+        for line in lines {
+            self.send_fields(time, line, send, |column_id| seen.insert(column_id)).await?;
         }
 
         Ok(())
@@ -0,0 +1,37 @@
+use std::path::Path;
+
+use anyhow::{Context as _, Result};
+use futures::channel::mpsc;
+use futures::SinkExt as _;
+use tokio::fs;
+
+use super::follow::Follow;
+use super::{Message, SeriesKey, Value, WorkerBuilder};
+
+pub async fn open(
+    path: &Path,
+    send: &mpsc::Sender<Message>,
+    follow: Option<Follow>,
+) -> Result<WorkerBuilder> {
+    let fd = fs::File::open(path).await.context("cannot open file for reading")?;
+    let key = SeriesKey::new(path.display().to_string());
+    let mut send = send.clone();
+
+    Ok(Box::new(move |warnings, cancel| {
+        Box::pin(async move {
+            let mut read = super::thread_line_reader(fd, follow, cancel, warnings).await;
+
+            while let Some((line, time)) = read.recv().await {
+                let text = line.trim();
+                if text.is_empty() {
+                    continue;
+                }
+
+                let value = Value::Annotation(text.to_string());
+                send.send(Message { key: key.clone(), value, time }).await?;
+            }
+
+            Ok(())
+        })
+    }))
+}
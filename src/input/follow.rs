@@ -0,0 +1,142 @@
+use std::fs::File;
+use std::io::{self, BufRead as _, BufReader, Seek as _, SeekFrom};
+use std::mem;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context as _, Result};
+use futures::{select, FutureExt as _};
+use tokio::runtime;
+use tokio_util::sync::CancellationToken;
+
+use super::notifier::{Notifier, WatchHandle};
+use super::{State, WarningSender};
+use crate::util;
+
+/// Keeps reading a regular file after reaching its end, like `tail -F`.
+pub struct Follow {
+    path:        PathBuf,
+    /// Also notified when the file is replaced, since the parent directory is watched.
+    watch:       WatchHandle<notify::RecommendedWatcher>,
+    poll_period: Duration,
+    from_end:    bool,
+    /// The number of leading lines, such as a header, skipped when the file is reopened.
+    skip_lines:  usize,
+}
+
+impl Follow {
+    pub fn new(
+        path: PathBuf,
+        notifier: &Notifier<notify::RecommendedWatcher>,
+        poll_period: Duration,
+        from_end: bool,
+    ) -> Result<Self> {
+        let watch = notifier.watch(&path)?;
+        Ok(Self { path, watch, poll_period, from_end, skip_lines: 0 })
+    }
+
+    #[must_use]
+    pub fn with_skipped_lines(self, skip_lines: usize) -> Self { Self { skip_lines, ..self } }
+
+    /// Reads lines from `file` until cancelled,
+    /// waiting for appends at the end of the file and reopening it when it is rotated.
+    pub fn read_lines(
+        mut self,
+        file: File,
+        runtime: &runtime::Handle,
+        cancel: &CancellationToken,
+        send: &tokio::sync::mpsc::Sender<(String, SystemTime)>,
+        warn_send: &mut WarningSender,
+    ) {
+        let mut reader = BufReader::new(file);
+        if self.from_end {
+            if let Err(err) = reader.seek(SeekFrom::End(0)) {
+                warn_send.error(format!("seek to end: {err:?}"));
+            }
+        }
+
+        // incomplete last line of the file, completed by the next append
+        let mut line = String::new();
+        let mut skip_lines = 0;
+        while !cancel.is_cancelled() {
+            match reader.read_line(&mut line) {
+                Ok(_) if line.ends_with('\n') => {
+                    let line = mem::take(&mut line);
+                    if skip_lines > 0 {
+                        skip_lines -= 1;
+                        continue;
+                    }
+                    if send.blocking_send((line, SystemTime::now())).is_err() {
+                        return;
+                    }
+                }
+                Ok(_) => {
+                    runtime.block_on(self.wait(cancel));
+                    match self.reopen(&mut reader, warn_send) {
+                        Ok(false) => {}
+                        Ok(true) => {
+                            // the old file ended without a final newline, so its last line is complete
+                            let mut line = mem::take(&mut line);
+                            if !line.is_empty() && skip_lines == 0 {
+                                line.push('\n');
+                                if send.blocking_send((line, SystemTime::now())).is_err() {
+                                    return;
+                                }
+                            }
+                            skip_lines = self.skip_lines;
+                        }
+                        Err(err) => warn_send.error(format!("{err:?}")),
+                    }
+                }
+                Err(err) => {
+                    warn_send.error(format!("{err:?}"));
+                    runtime.block_on(self.wait(cancel));
+                }
+            }
+        }
+    }
+
+    /// Waits for a change to the file, or for the poll period
+    /// in case the change is not reported by the watcher.
+    async fn wait(&mut self, cancel: &CancellationToken) {
+        select! {
+            () = cancel.cancelled().fuse() => {},
+            () = tokio::time::sleep(self.poll_period).fuse() => {},
+            () = self.watch.wait() => {},
+        }
+    }
+
+    /// Rewinds the reader if the file was truncated,
+    /// or opens the new file at the path if the file was rotated.
+    ///
+    /// Returns whether reading restarted from the beginning of a file.
+    fn reopen(&self, reader: &mut BufReader<File>, warn_send: &WarningSender) -> Result<bool> {
+        let position = reader.stream_position().context("get read position")?;
+        let current = reader.get_ref().metadata().context("stat open file")?;
+
+        let at_path = match std::fs::metadata(&self.path) {
+            Ok(metadata) => metadata,
+            // the file is being rotated, keep reading the old file until it is recreated
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                warn_send.set_state(State::WaitingForFile);
+                return Ok(false);
+            }
+            Err(err) => return Err(err).context("stat file"),
+        };
+        warn_send.set_state(State::Running);
+
+        // drain the old file before switching, in case it was appended to before the rotation
+        if !util::same_file(&current, &at_path) && position >= current.len() {
+            let file = File::open(&self.path).context("reopen rotated file")?;
+            *reader = BufReader::new(file);
+            return Ok(true);
+        }
+
+        if current.len() < position {
+            reader.seek(SeekFrom::Start(0)).context("rewind truncated file")?;
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context as _, Result};
+use futures::channel::mpsc;
+use futures::{future, select, FutureExt as _};
+use regex::Regex;
+use tokio::{fs, time};
+use tokio_util::sync::CancellationToken;
+
+use super::notifier::Notifier;
+use super::{Kind, Message, Opener, Spec, WorkerBuilder};
+use crate::util;
+
+/// A path pattern where `*` and `?` in a component match file names in a directory.
+struct GlobPattern {
+    pattern:  PathBuf,
+    /// Matches an expanded path, capturing each component with wildcards.
+    captures: Regex,
+}
+
+impl GlobPattern {
+    fn new(pattern: &Path) -> Result<Self> {
+        let text = pattern.to_str().context("glob pattern must be valid UTF-8")?;
+        let components: Vec<_> = text
+            .split('/')
+            .map(|component| {
+                if !component.contains(['*', '?']) {
+                    return regex::escape(component);
+                }
+                let regex: String = component
+                    .chars()
+                    .map(|char| match char {
+                        '*' => "[^/]*".to_string(),
+                        '?' => "[^/]".to_string(),
+                        char => regex::escape(&char.to_string()),
+                    })
+                    .collect();
+                format!("({regex})")
+            })
+            .collect();
+        let regex = format!("^{}$", components.join("/"));
+
+        Ok(Self { pattern: pattern.to_path_buf(), captures: Regex::new(&regex)? })
+    }
+
+    /// The deepest directory that contains all matching files.
+    fn base_dir(&self) -> PathBuf {
+        let literal = self
+            .pattern
+            .components()
+            .take_while(|component| !has_wildcard(component))
+            .collect::<PathBuf>();
+        if literal == self.pattern {
+            literal.parent().map(Path::to_path_buf).unwrap_or_default()
+        } else {
+            literal
+        }
+    }
+
+    /// Lists the regular files currently matching the pattern, along with their labels.
+    ///
+    /// The label is the path components matched by components with wildcards, joined by `/`,
+    /// or the file name if the pattern has no wildcards.
+    async fn expand(&self) -> Vec<(PathBuf, String)> {
+        let mut paths = vec![PathBuf::new()];
+        for component in self.pattern.components() {
+            if !has_wildcard(&component) {
+                for path in &mut paths {
+                    path.push(component);
+                }
+                continue;
+            }
+
+            let pattern = component.as_os_str().to_string_lossy();
+            let mut matches = Vec::new();
+            for dir in &paths {
+                // directories that cannot be read have no matches
+                let Ok(mut entries) =
+                    fs::read_dir(if dir.as_os_str().is_empty() { Path::new(".") } else { dir })
+                        .await
+                else {
+                    continue;
+                };
+                while let Ok(Some(entry)) = entries.next_entry().await {
+                    if util::glob_match(&pattern, &entry.file_name().to_string_lossy()) {
+                        matches.push(dir.join(entry.file_name()));
+                    }
+                }
+            }
+            paths = matches;
+        }
+
+        let mut files = Vec::new();
+        for path in paths {
+            if fs::metadata(&path).await.is_ok_and(|metadata| metadata.is_file()) {
+                files.push(path);
+            }
+        }
+        files.sort();
+        files
+            .into_iter()
+            .map(|path| {
+                let label = self.label(&path);
+                (path, label)
+            })
+            .collect()
+    }
+
+    fn label(&self, path: &Path) -> String {
+        let text = path.to_string_lossy();
+        let captures: Vec<_> = match self.captures.captures(&text) {
+            Some(captures) => {
+                captures.iter().skip(1).flatten().map(|capture| capture.as_str()).collect()
+            }
+            None => Vec::new(),
+        };
+        if captures.is_empty() {
+            path.file_name().unwrap_or_default().to_string_lossy().into_owned()
+        } else {
+            captures.join("/")
+        }
+    }
+}
+
+fn has_wildcard(component: &Component) -> bool {
+    component.as_os_str().to_string_lossy().contains(['*', '?'])
+}
+
+impl Spec {
+    /// The input for a file matching the glob of this spec.
+    ///
+    /// CSV files without a header have a single column named by the label,
+    /// and other files have their series labels prefixed by the label.
+    fn matched_input(&self, path: PathBuf, label: String) -> Self {
+        let prefix = self.label_prefix.clone().unwrap_or_default();
+        let (header, label_prefix) = match (self.kind, &self.header) {
+            (Kind::CsvPoll, None) => (Some(label), self.label_prefix.clone()),
+            _ => (self.header.clone(), Some(format!("{prefix}{label}."))),
+        };
+        Self { path, glob: false, header, label_prefix, ..self.clone() }
+    }
+}
+
+/// Opens each file matching the glob of `spec` as a separate input,
+/// rescanning when the base directory changes or every `poll_period`.
+///
+/// Inputs of files that no longer match are stopped.
+pub fn open(
+    opener: &Opener,
+    spec: &Spec,
+    poll_period: Duration,
+    notifier: &Notifier<notify::RecommendedWatcher>,
+    send: &mpsc::Sender<Message>,
+) -> Result<WorkerBuilder> {
+    let pattern = GlobPattern::new(&spec.path)?;
+    // the base directory may be created later, or not report changes at all like sysfs
+    let mut watcher = notifier.watch_dir(&pattern.base_dir()).ok();
+
+    let opener = opener.clone();
+    let notifier = notifier.clone();
+    let spec = spec.clone();
+    let send = send.clone();
+
+    Ok(Box::new(move |mut warnings, cancel| {
+        Box::pin(async move {
+            // keep this input active while more files may be added
+            let _send = send;
+
+            let mut inputs = HashMap::<PathBuf, CancellationToken>::new();
+            let mut timer = time::interval(poll_period);
+
+            loop {
+                if watcher.is_none() {
+                    watcher = notifier.watch_dir(&pattern.base_dir()).ok();
+                }
+                let changed = async {
+                    match &mut watcher {
+                        Some(watcher) => watcher.wait().await,
+                        None => future::pending().await,
+                    }
+                };
+                select! {
+                    () = cancel.cancelled().fuse() => break,
+                    _ = timer.tick().fuse() => {},
+                    () = changed.fuse() => {},
+                }
+
+                let matches = pattern.expand().await;
+                inputs.retain(|path, input_cancel| {
+                    let exists = matches.iter().any(|(match_path, _)| match_path == path);
+                    if !exists {
+                        input_cancel.cancel();
+                    }
+                    exists
+                });
+
+                for (path, label) in matches {
+                    if inputs.contains_key(&path) {
+                        continue;
+                    }
+
+                    // not retried on failure to avoid repeating the warning on every scan
+                    let input_cancel = cancel.child_token();
+                    inputs.insert(path.clone(), input_cancel.clone());
+                    let input = spec.matched_input(path, label);
+                    if let Err(err) = opener.open_with(&input, input_cancel).await {
+                        warnings.error(format!("Error: {err:?}"));
+                    }
+                }
+            }
+
+            Ok(())
+        })
+    }))
+}
@@ -2,51 +2,86 @@ use std::fmt;
 use std::marker::PhantomData;
 use std::path::PathBuf;
 use std::result::Result as StdResult;
+use std::sync::Arc;
 use std::time::SystemTime;
 
 use anyhow::{Context as _, Result};
 use futures::channel::mpsc;
-use futures::{Future, SinkExt as _};
+use futures::SinkExt as _;
 use serde::{de, Deserialize};
 use tokio::fs;
 
+use super::follow::Follow;
+use super::long::LongFormat;
 use super::notifier::FieldParser;
-use super::{Message, WorkerBuilder};
-
-pub async fn open(path: PathBuf, send: &mpsc::Sender<Message>) -> Result<WorkerBuilder> {
+use super::{Message, SeriesKey, Value, WorkerBuilder};
+
+pub async fn open(
+    path: PathBuf,
+    send: &mpsc::Sender<Message>,
+    annotation_keys: Arc<[String]>,
+    long: Option<LongFormat>,
+    follow: Option<Follow>,
+) -> Result<WorkerBuilder> {
     let fd = fs::File::open(&path).await.context("cannot open file for reading")?;
+    Ok(stream(fd, send, annotation_keys, long, follow))
+}
+
+/// Reads JSON Lines from an open file, such as the output of a command.
+pub fn stream(
+    fd: fs::File,
+    send: &mpsc::Sender<Message>,
+    annotation_keys: Arc<[String]>,
+    long: Option<LongFormat>,
+    follow: Option<Follow>,
+) -> WorkerBuilder {
     let mut send = send.clone();
 
-    Ok(Box::new(move |mut warnings, cancel| {
+    Box::new(move |mut warnings, cancel| {
         Box::pin(async move {
             // TODO: support non-JSONLines streams of JSON objects
-            let mut read = super::thread_line_reader(fd, cancel, warnings.clone()).await;
+            let mut read = super::thread_line_reader(fd, follow, cancel, warnings.clone()).await;
 
             while let Some((line, time)) = read.recv().await {
-                if let Err(err) = send_fields(time, &line, &mut send).await {
-                    warnings.send(format!("Error: {err:?}"));
+                let result = match &long {
+                    Some(long) => send_long(time, &line, &mut send, long).await,
+                    None => send_fields(time, &line, &mut send, &annotation_keys).await,
+                };
+                if let Err(err) = result {
+                    warnings.error(format!("Error: {err:?}"));
                 }
             }
 
             Ok(())
         })
-    }))
+    })
 }
 
-pub struct PollParser;
+pub struct PollParser {
+    pub annotation_keys: Arc<[String]>,
+    pub long:            Option<LongFormat>,
+}
 
 impl FieldParser for PollParser {
-    fn parse(
+    async fn parse(
         &self,
         time: SystemTime,
         content: &str,
         send: &mut mpsc::Sender<Message>,
-    ) -> impl Future<Output = Result<()>> + Send {
-        send_fields(time, content, send)
+    ) -> Result<()> {
+        match &self.long {
+            Some(long) => send_long(time, content, send, long).await,
+            None => send_fields(time, content, send, &self.annotation_keys).await,
+        }
     }
 }
 
-async fn send_fields(time: SystemTime, json: &str, send: &mut mpsc::Sender<Message>) -> Result<()> {
+async fn send_fields(
+    time: SystemTime,
+    json: &str,
+    send: &mut mpsc::Sender<Message>,
+    annotation_keys: &[String],
+) -> Result<()> {
     if json.is_empty() {
         return Ok(());
     }
@@ -62,18 +97,48 @@ async fn send_fields(time: SystemTime, json: &str, send: &mut mpsc::Sender<Messa
     };
 
     for (label, field) in fields {
-        if let MaybeNumber::Number(value) = field {
-            let message = Message { label, value, time };
-            send.feed(message).await?;
-        } else {
-            log::debug!("Key {label:?} is not a number");
-        }
+        let value = match field {
+            MaybeNumber::Number(value) => Value::Number(value),
+            MaybeNumber::String(text) if annotation_keys.contains(&label) => {
+                Value::Annotation(text)
+            }
+            _ => {
+                log::debug!("Key {label:?} is not a number");
+                continue;
+            }
+        };
+        send.feed(Message { key: SeriesKey::parse(&label), value, time }).await?;
     }
     send.flush().await?;
 
     Ok(())
 }
 
+/// Sends a JSON object in long format as a single message.
+async fn send_long(
+    time: SystemTime,
+    json: &str,
+    send: &mut mpsc::Sender<Message>,
+    long: &LongFormat,
+) -> Result<()> {
+    if json.trim().is_empty() {
+        return Ok(());
+    }
+
+    let object: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(json).context("parsing JSON")?;
+    let field = |column: &str| match object.get(column)? {
+        serde_json::Value::String(string) => Some(string.clone()),
+        serde_json::Value::Null => None,
+        value => Some(value.to_string()),
+    };
+    if let Some(message) = long.message(field, time)? {
+        send.send(message).await?;
+    }
+
+    Ok(())
+}
+
 #[derive(Debug)]
 struct KeyValues<T>(Vec<(String, T)>);
 
@@ -111,5 +176,6 @@ impl<'de, T: Deserialize<'de>> Deserialize<'de> for KeyValues<T> {
 #[serde(untagged)]
 enum MaybeNumber {
     Number(f64),
+    String(String),
     NotNumber(de::IgnoredAny),
 }
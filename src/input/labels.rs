@@ -0,0 +1,79 @@
+use anyhow::{Context as _, Result};
+use regex::Regex;
+
+/// Renames and drops series by their names before they are plotted.
+#[derive(Debug)]
+pub struct LabelRules {
+    rename: Vec<(Regex, String)>,
+    drop:   Vec<Regex>,
+}
+
+impl LabelRules {
+    pub fn new(rename: Vec<(Regex, String)>, drop: Vec<Regex>) -> Self { Self { rename, drop } }
+
+    /// Applies the rename rules to `name` in order,
+    /// returning whether the renamed series should be kept.
+    pub fn apply(&self, name: &mut String) -> bool {
+        for (regex, replacement) in &self.rename {
+            if let std::borrow::Cow::Owned(renamed) = regex.replace_all(name, replacement) {
+                *name = renamed;
+            }
+        }
+        !self.drop.iter().any(|regex| regex.is_match(name))
+    }
+}
+
+pub fn parse_rename_rule(arg: &str) -> Result<(Regex, String)> {
+    // the replacement is more likely to be free of `=` than the regex
+    let (regex, replacement) = arg
+        .rsplit_once('=')
+        .context("--rename argument should be in the form `REGEX=REPLACEMENT`")?;
+    Ok((Regex::new(regex).context("invalid --rename regex")?, replacement.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use super::{parse_rename_rule, LabelRules};
+
+    fn apply(rules: &LabelRules, name: &str) -> Option<String> {
+        let mut name = name.to_string();
+        rules.apply(&mut name).then_some(name)
+    }
+
+    #[test]
+    fn rename_in_order() {
+        let rules = LabelRules::new(
+            vec![
+                parse_rename_rule(r"^cpu(\d+)\.=core$1.").unwrap(),
+                parse_rename_rule(r"^core0\.=first.").unwrap(),
+            ],
+            Vec::new(),
+        );
+        assert_eq!(apply(&rules, "cpu0.user").as_deref(), Some("first.user"));
+        assert_eq!(apply(&rules, "cpu1.user").as_deref(), Some("core1.user"));
+        assert_eq!(apply(&rules, "mem.used").as_deref(), Some("mem.used"));
+    }
+
+    #[test]
+    fn drop_after_rename() {
+        let rules = LabelRules::new(
+            vec![parse_rename_rule("^tmp_=debug.").unwrap()],
+            vec![Regex::new(r"^debug\.").unwrap()],
+        );
+        assert_eq!(apply(&rules, "tmp_x"), None);
+        assert_eq!(apply(&rules, "debug.y"), None);
+        assert_eq!(apply(&rules, "x").as_deref(), Some("x"));
+    }
+
+    #[test]
+    fn rename_rule_splits_at_last_equals() {
+        let (regex, replacement) = parse_rename_rule("a=b=c").unwrap();
+        assert_eq!(regex.as_str(), "a=b");
+        assert_eq!(replacement, "c");
+
+        assert!(parse_rename_rule("no-replacement").is_err());
+        assert!(parse_rename_rule("(=x").is_err());
+    }
+}
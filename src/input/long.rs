@@ -0,0 +1,69 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context as _, Result};
+
+use super::{Message, SeriesKey, Value};
+
+/// Columns of a long-format input, where each row is one observation of a series.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LongFormat {
+    /// Columns whose values form the label of the series.
+    ///
+    /// The value of the first column is the metric name,
+    /// and the other columns are tags, e.g. `cpu{host=a}` for the columns `metric,host`.
+    pub labels: Vec<String>,
+    /// Column of the numeric value.
+    #[serde(default = "default_value_column")]
+    pub value:  String,
+    /// Column of the observation time, as Unix seconds or an RFC 3339 timestamp.
+    ///
+    /// Defaults to the time the row is read.
+    pub time:   Option<String>,
+}
+
+fn default_value_column() -> String { "value".into() }
+
+impl LongFormat {
+    /// Converts a row into a message, where `field` returns the value of a column by name.
+    ///
+    /// Returns `None` if the value column is not a number.
+    pub fn message(
+        &self,
+        field: impl Fn(&str) -> Option<String>,
+        read_time: SystemTime,
+    ) -> Result<Option<Message>> {
+        let column = |name: &str| field(name).with_context(|| format!("missing column {name:?}"));
+
+        let Some(value) = column(&self.value)?.trim().parse().ok() else { return Ok(None) };
+
+        let (name_column, tag_columns) =
+            self.labels.split_first().context("long format requires a label column")?;
+        let name = column(name_column)?;
+        let tags = tag_columns
+            .iter()
+            .map(|tag| Ok((tag.clone(), column(tag)?)))
+            .collect::<Result<BTreeMap<_, _>>>()?;
+
+        let time = match &self.time {
+            Some(time_column) => parse_time(&column(time_column)?)?,
+            None => read_time,
+        };
+
+        Ok(Some(Message { key: SeriesKey { name, tags }, value: Value::Number(value), time }))
+    }
+}
+
+/// Parses Unix seconds or an RFC 3339 timestamp.
+fn parse_time(time: &str) -> Result<SystemTime> {
+    let time = time.trim();
+    if let Ok(secs) = time.parse::<f64>() {
+        let duration = Duration::try_from_secs_f64(secs).context("invalid Unix timestamp")?;
+        return Ok(SystemTime::UNIX_EPOCH + duration);
+    }
+
+    let time = chrono::DateTime::parse_from_rfc3339(time)
+        .with_context(|| format!("invalid timestamp {time:?}"))?;
+    Ok(time.into())
+}
@@ -1,23 +1,26 @@
 use std::collections::{hash_map, HashMap};
+use std::fs::Metadata;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{self, AtomicU64};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
+use std::{io, mem};
 
 use anyhow::{Context as _, Result};
 use futures::channel::mpsc;
 use futures::future::FusedFuture;
-use futures::{select, Future, FutureExt as _};
+use futures::{future, select, Future, FutureExt as _};
 use notify::Watcher;
 use parking_lot::{Mutex, RwLock};
 use tokio::{fs, time};
 
-use super::{Message, WarningSender, WorkerBuilder};
+use super::{Message, State, WarningSender, WorkerBuilder};
 use crate::util;
 
-pub fn start(
-    warnings: WarningSender,
-) -> Result<Notifier<impl notify::Watcher + Send + Sync + 'static>> {
+/// How often a removed directory is checked for being recreated.
+const REARM_PERIOD: Duration = Duration::from_secs(1);
+
+pub fn start(warnings: WarningSender) -> Result<Notifier<notify::RecommendedWatcher>> {
     let senders = Arc::new(RwLock::new(AllSenders::default()));
 
     let watcher = notify::recommended_watcher(Handler { warnings, senders: senders.clone() })
@@ -35,9 +38,32 @@ pub struct Notifier<W> {
     watcher:    Arc<Mutex<W>>,
 }
 
+impl<W> Clone for Notifier<W> {
+    fn clone(&self) -> Self {
+        Self {
+            watcher_id: self.watcher_id.clone(),
+            senders:    self.senders.clone(),
+            watcher:    self.watcher.clone(),
+        }
+    }
+}
+
 #[derive(Default)]
 struct AllSenders {
-    paths: HashMap<PathBuf, PathSenders>,
+    /// Watchers of a file, notified when it is modified, created, removed or replaced.
+    files:       HashMap<PathBuf, PathSenders>,
+    /// Watchers of a directory, notified when its entries are created, removed or renamed.
+    dirs:        HashMap<PathBuf, PathSenders>,
+    /// Directories registered with the watcher.
+    directories: HashMap<PathBuf, Directory>,
+}
+
+struct Directory {
+    /// Number of handles depending on the registration.
+    handles:  usize,
+    /// The registered directory, to detect it being replaced by another directory at its path,
+    /// which is not watched.
+    metadata: Metadata,
 }
 
 #[derive(Default)]
@@ -45,71 +71,202 @@ struct PathSenders {
     watchers: HashMap<WatcherId, WatchHandleSender>,
 }
 
+/// What a [`WatchHandle`] is notified about.
+enum Target {
+    File(PathBuf),
+    Dir(PathBuf),
+}
+
+impl Target {
+    /// The directory registered with the watcher.
+    ///
+    /// Files are watched through their parent directory,
+    /// so that the watch survives the file being replaced or deleted.
+    fn directory(&self) -> &Path {
+        match self {
+            Self::File(path) => path.parent().expect("file paths are joined to a directory"),
+            Self::Dir(path) => path,
+        }
+    }
+
+    fn senders<'a>(&self, all: &'a mut AllSenders) -> hash_map::Entry<'a, PathBuf, PathSenders> {
+        match self {
+            Self::File(path) => all.files.entry(path.clone()),
+            Self::Dir(path) => all.dirs.entry(path.clone()),
+        }
+    }
+}
+
 impl<W: notify::Watcher + Send + Sync + 'static> Notifier<W> {
+    /// Notifies about changes to the file at `path`, including replacement by another file.
+    ///
+    /// The parent directory of the file must exist, but the file itself need not.
     pub fn watch(&self, path: &Path) -> Result<WatchHandle<W>> {
+        let name = path.file_name().with_context(|| format!("{} is not a file", path.display()))?;
+        let dir = canonical_dir(path.parent().unwrap_or(Path::new("")))?;
+        self.register(Target::File(dir.join(name)))
+    }
+
+    /// Notifies about entries of the directory at `dir` being created, removed or renamed.
+    pub fn watch_dir(&self, dir: &Path) -> Result<WatchHandle<W>> {
+        self.register(Target::Dir(canonical_dir(dir)?))
+    }
+
+    fn register(&self, target: Target) -> Result<WatchHandle<W>> {
         let id = WatcherId(self.watcher_id.fetch_add(1, atomic::Ordering::SeqCst));
         let (event_send, event_recv) = mpsc::channel(16);
+        let dir = target.directory();
+
+        // the watcher lock serializes registration, while the senders lock must not be held
+        // when the watcher waits for its event loop, which may be blocked in the event handler
+        let mut watcher = self.watcher.lock();
+        let metadata = if self.senders.read().directories.contains_key(dir) {
+            None
+        } else {
+            watcher
+                .watch(dir, notify::RecursiveMode::NonRecursive)
+                .with_context(|| format!("register watcher for {}", dir.display()))?;
+            Some(std::fs::metadata(dir).with_context(|| format!("stat {}", dir.display()))?)
+        };
+        {
+            let mut all_senders = self.senders.write();
+            match all_senders.directories.entry(dir.to_owned()) {
+                hash_map::Entry::Occupied(mut entry) => entry.get_mut().handles += 1,
+                hash_map::Entry::Vacant(entry) => {
+                    let metadata = metadata.expect("unregistered directory was registered above");
+                    entry.insert(Directory { handles: 1, metadata });
+                }
+            }
+            target
+                .senders(&mut all_senders)
+                .or_default()
+                .watchers
+                .insert(id, WatchHandleSender { event_ch: event_send });
+        }
+        drop(watcher);
 
-        let handle = WatchHandle {
+        // constructed only after registration, since dropping the handle unregisters it
+        Ok(WatchHandle {
             id,
-            path: path.to_owned(),
+            target,
             senders: self.senders.clone(),
             watcher: self.watcher.clone(),
             events: Some(event_recv),
-        };
-
-        let mut all_senders = self.senders.write();
-        let path_entry = all_senders.paths.entry(path.to_owned());
-        let path_senders = match path_entry {
-            hash_map::Entry::Occupied(entry) => entry.into_mut(),
-            hash_map::Entry::Vacant(entry) => {
-                let mut watcher = self.watcher.lock();
-                watcher
-                    .watch(path, notify::RecursiveMode::NonRecursive)
-                    .with_context(|| format!("register watcher for {}", path.display()))?;
-                entry.insert(<_>::default())
-            }
-        };
-        path_senders.watchers.insert(id, WatchHandleSender { event_ch: event_send });
-
-        Ok(handle)
+        })
     }
 }
 
+/// Resolves a directory to the form of the paths in the events reported by the watcher.
+fn canonical_dir(dir: &Path) -> Result<PathBuf> {
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    dir.canonicalize().with_context(|| format!("resolve directory {}", dir.display()))
+}
+
 struct WatchHandleSender {
     event_ch: mpsc::Sender<()>,
 }
 
 pub struct WatchHandle<W: notify::Watcher> {
     id:      WatcherId,
-    path:    PathBuf,
+    target:  Target,
     senders: Arc<RwLock<AllSenders>>,
     watcher: Arc<Mutex<W>>,
     events:  Option<mpsc::Receiver<()>>,
 }
 
 impl<W: notify::Watcher> WatchHandle<W> {
+    /// Waits for a change.
+    ///
+    /// While the watched directory is removed, this returns every [`REARM_PERIOD`] instead,
+    /// so that the directory is watched again by the next call after it is recreated.
     pub fn wait(&mut self) -> impl FusedFuture<Output = ()> + '_ {
-        util::some_or_pending(&mut self.events).fuse()
+        let removed = !self.rearm();
+        let events = &mut self.events;
+        async move {
+            select! {
+                () = util::some_or_pending(events).fuse() => {},
+                () = async {
+                    if removed {
+                        time::sleep(REARM_PERIOD).await;
+                    } else {
+                        future::pending::<()>().await;
+                    }
+                }.fuse() => {},
+            }
+        }
+        .fuse()
+    }
+
+    /// Registers the directory with the watcher again if it was replaced at its path,
+    /// e.g. removed and recreated, since the watch stays with the old directory.
+    ///
+    /// Returns whether the directory exists and is watched.
+    fn rearm(&self) -> bool {
+        let dir = self.target.directory();
+        let Ok(current) = std::fs::metadata(dir) else { return false };
+        let registered = |all_senders: &AllSenders| {
+            all_senders
+                .directories
+                .get(dir)
+                .is_some_and(|registered| util::same_file(&registered.metadata, &current))
+        };
+        if registered(&self.senders.read()) {
+            return true;
+        }
+
+        let mut watcher = self.watcher.lock();
+        // another handle of the same directory may have registered it while waiting for the lock
+        if registered(&self.senders.read()) {
+            return true;
+        }
+        // fails if the watcher already dropped the watch when the directory was removed
+        _ = watcher.unwatch(dir);
+        if watcher.watch(dir, notify::RecursiveMode::NonRecursive).is_err() {
+            return false;
+        }
+        let mut all_senders = self.senders.write();
+        if let Some(registered) = all_senders.directories.get_mut(dir) {
+            registered.metadata = current;
+        }
+        // entries may have been created before the registration
+        notify_dir(&all_senders, dir);
+        true
     }
 }
 
 impl<W: notify::Watcher> Drop for WatchHandle<W> {
     fn drop(&mut self) {
-        let mut all_senders = self.senders.write();
-        let hash_map::Entry::Occupied(mut path_senders) =
-            all_senders.paths.entry(self.path.clone())
-        else {
-            panic!("some watcher not dropped yet but PathSenders is removed")
+        let dir = self.target.directory();
+
+        let mut watcher = self.watcher.lock();
+        let unused = {
+            let mut all_senders = self.senders.write();
+            let hash_map::Entry::Occupied(mut path_senders) = self.target.senders(&mut all_senders)
+            else {
+                panic!("some watcher not dropped yet but PathSenders is removed")
+            };
+            path_senders.get_mut().watchers.remove(&self.id);
+            if path_senders.get().watchers.is_empty() {
+                path_senders.remove();
+            }
+
+            let hash_map::Entry::Occupied(mut registered) =
+                all_senders.directories.entry(dir.into())
+            else {
+                panic!("some watcher not dropped yet but the directory is unregistered")
+            };
+            registered.get_mut().handles -= 1;
+            let unused = registered.get().handles == 0;
+            if unused {
+                registered.remove();
+            }
+            unused
         };
-        path_senders.get_mut().watchers.remove(&self.id);
-        if path_senders.get_mut().watchers.is_empty() {
-            path_senders.remove();
-            {
-                let mut watcher = self.watcher.lock();
-                if let Err(err) = watcher.unwatch(&self.path) {
-                    eprintln!("Error closing watcher: {err:?}");
-                }
+        if unused {
+            match watcher.unwatch(dir) {
+                // the watch is removed automatically when the directory is deleted
+                Ok(()) | Err(notify::Error { kind: notify::ErrorKind::WatchNotFound, .. }) => {}
+                Err(err) => eprintln!("Error closing watcher: {err:?}"),
             }
         }
     }
@@ -122,20 +279,49 @@ struct Handler {
 
 impl notify::EventHandler for Handler {
     fn handle_event(&mut self, event: notify::Result<notify::Event>) {
-        match event {
-            Ok(event) => {
-                if matches!(event.kind, notify::EventKind::Modify(..)) {
-                    let all_senders = self.senders.read();
-                    for path in event.paths {
-                        if let Some(path_senders) = all_senders.paths.get(&path) {
-                            for sender in path_senders.watchers.values() {
-                                _ = sender.event_ch.clone().try_send(());
-                            }
-                        }
-                    }
-                }
+        use notify::event::{EventKind, ModifyKind};
+
+        let event = match event {
+            Ok(event) => event,
+            Err(err) => return self.warnings.error(format!("{err:?}")),
+        };
+        if matches!(event.kind, EventKind::Access(..)) {
+            return;
+        }
+        let entries_changed = matches!(
+            event.kind,
+            EventKind::Create(..) | EventKind::Remove(..) | EventKind::Modify(ModifyKind::Name(..))
+        );
+
+        let all_senders = self.senders.read();
+        // renames report both the old and the new path, notifying watchers of either
+        for path in &event.paths {
+            notify(all_senders.files.get(path));
+            if entries_changed {
+                notify(path.parent().and_then(|dir| all_senders.dirs.get(dir)));
+            }
+            // wake up the handles of a removed directory to watch for it being recreated
+            if matches!(event.kind, EventKind::Remove(..))
+                && all_senders.directories.contains_key(path)
+            {
+                notify_dir(&all_senders, path);
             }
-            Err(err) => self.warnings.send(format!("{err:?}")),
+        }
+    }
+}
+
+fn notify(path_senders: Option<&PathSenders>) {
+    for sender in path_senders.into_iter().flat_map(|senders| senders.watchers.values()) {
+        _ = sender.event_ch.clone().try_send(());
+    }
+}
+
+/// Notifies the watchers of the directory `dir` and of the files in it.
+fn notify_dir(all_senders: &AllSenders, dir: &Path) {
+    notify(all_senders.dirs.get(dir));
+    for (path, path_senders) in &all_senders.files {
+        if path.parent() == Some(dir) {
+            notify(Some(path_senders));
         }
     }
 }
@@ -172,6 +358,8 @@ pub fn open_poll(
     Ok(Box::new(move |mut warnings, cancel| {
         Box::pin(async move {
             let mut timer = time::interval(poll_period);
+            // reported once instead of failing to read on every poll
+            let mut missing = false;
 
             loop {
                 select! {
@@ -180,8 +368,20 @@ pub fn open_poll(
                     () = watcher.wait().fuse() => {},
                 }
 
-                if let Err(err) = read_once(&path, &mut send, &parser).await {
-                    warnings.send(format!("{err:?}"));
+                match read_once(&path, &mut send, &parser).await {
+                    Ok(()) => {
+                        if mem::take(&mut missing) {
+                            warnings.send("file is available again");
+                            warnings.set_state(State::Running);
+                        }
+                    }
+                    Err(err) if is_not_found(&err) => {
+                        if !mem::replace(&mut missing, true) {
+                            warnings.send("file missing, waiting for it to be created");
+                            warnings.set_state(State::WaitingForFile);
+                        }
+                    }
+                    Err(err) => warnings.error(format!("{err:?}")),
                 }
             }
 
@@ -189,3 +389,9 @@ pub fn open_poll(
         })
     }))
 }
+
+fn is_not_found(err: &anyhow::Error) -> bool {
+    err.root_cause()
+        .downcast_ref::<io::Error>()
+        .is_some_and(|err| err.kind() == io::ErrorKind::NotFound)
+}
@@ -0,0 +1,144 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::sync::atomic::{self, AtomicBool};
+use std::time::{Duration, SystemTime};
+
+use parking_lot::Mutex;
+use tokio_util::sync::CancellationToken;
+
+use super::{Kind, SeriesKey, Spec};
+
+/// The number of recent seconds over which the message rate is measured.
+const RATE_WINDOW_SECS: u64 = 10;
+
+/// The activity of an input, shared between its worker and the UI.
+pub struct InputStatus {
+    pub kind: Kind,
+    pub path: String,
+    /// Messages from a paused input are dropped,
+    /// while the series already received are kept.
+    paused:   AtomicBool,
+    cancel:   CancellationToken,
+    inner:    Mutex<Inner>,
+}
+
+struct Inner {
+    state:        State,
+    messages:     u64,
+    /// Number of messages received in each recent second, by Unix second.
+    recent:       VecDeque<(u64, u64)>,
+    last_message: Option<SystemTime>,
+    series:       HashSet<SeriesKey>,
+    last_error:   Option<(SystemTime, String)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    Running,
+    /// The input has been read to the end.
+    Eof,
+    /// The worker stopped with an error.
+    Errored,
+    /// The file does not exist, and is read again once it is created.
+    WaitingForFile,
+    /// The input was cancelled, e.g. because its file no longer matches a glob.
+    Stopped,
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Running => "running",
+            Self::Eof => "EOF",
+            Self::Errored => "errored",
+            Self::WaitingForFile => "waiting for file",
+            Self::Stopped => "stopped",
+        })
+    }
+}
+
+/// A consistent copy of an [`InputStatus`] for display.
+pub struct Snapshot {
+    pub state:        State,
+    pub messages:     u64,
+    /// Messages per second over the recent seconds.
+    pub rate:         f64,
+    pub last_message: Option<SystemTime>,
+    pub series:       usize,
+    pub last_error:   Option<(SystemTime, String)>,
+}
+
+impl InputStatus {
+    pub fn new(spec: &Spec, cancel: CancellationToken) -> Self {
+        Self {
+            kind: spec.kind,
+            path: spec.path.display().to_string(),
+            paused: AtomicBool::new(false),
+            cancel,
+            inner: Mutex::new(Inner {
+                state:        State::Running,
+                messages:     0,
+                recent:       VecDeque::new(),
+                last_message: None,
+                series:       HashSet::new(),
+                last_error:   None,
+            }),
+        }
+    }
+
+    pub fn set_state(&self, state: State) { self.inner.lock().state = state; }
+
+    pub fn is_paused(&self) -> bool { self.paused.load(atomic::Ordering::Relaxed) }
+
+    pub fn toggle_pause(&self) { self.paused.fetch_xor(true, atomic::Ordering::Relaxed); }
+
+    /// Stops the worker of the input, along with the inputs it opened.
+    pub fn stop(&self) { self.cancel.cancel(); }
+
+    pub fn record_error(&self, error: String) {
+        self.inner.lock().last_error = Some((SystemTime::now(), error));
+    }
+
+    pub fn record_message(&self, key: &SeriesKey) {
+        let now = SystemTime::now();
+        let second = unix_secs(now);
+
+        let mut inner = self.inner.lock();
+        inner.messages += 1;
+        inner.last_message = Some(now);
+        match inner.recent.back_mut() {
+            Some((last, count)) if *last == second => *count += 1,
+            _ => inner.recent.push_back((second, 1)),
+        }
+        while inner.recent.front().is_some_and(|&(first, _)| first + RATE_WINDOW_SECS <= second) {
+            inner.recent.pop_front();
+        }
+        if !inner.series.contains(key) {
+            inner.series.insert(key.clone());
+        }
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        let inner = self.inner.lock();
+        // the current second is incomplete, so only the preceding seconds are counted
+        let current = unix_secs(SystemTime::now());
+        let completed: u64 = inner
+            .recent
+            .iter()
+            .filter(|&&(second, _)| second < current && second + RATE_WINDOW_SECS > current)
+            .map(|&(_, count)| count)
+            .sum();
+        Snapshot {
+            state:        inner.state,
+            messages:     inner.messages,
+            rate:         completed as f64 / (RATE_WINDOW_SECS - 1) as f64,
+            last_message: inner.last_message,
+            series:       inner.series.len(),
+            last_error:   inner.last_error.clone(),
+        }
+    }
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or(Duration::ZERO).as_secs()
+}
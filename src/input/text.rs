@@ -0,0 +1,149 @@
+use std::path::Path;
+
+use anyhow::{Context as _, Result};
+use futures::channel::mpsc;
+use futures::SinkExt as _;
+use regex::Regex;
+use tokio::fs;
+
+use super::follow::Follow;
+use super::{Message, SeriesKey, Value, WorkerBuilder};
+
+/// Extracts numeric fields from unstructured log lines.
+pub enum LineParser {
+    /// Each named capture group of the regex is a series.
+    Pattern(Regex),
+    /// Each `key=value` pair in logfmt syntax is a series.
+    Logfmt,
+}
+
+impl LineParser {
+    pub fn pattern(pattern: &str) -> Result<Self> {
+        let regex = Regex::new(pattern).context("invalid --pattern regex")?;
+        anyhow::ensure!(
+            regex.capture_names().flatten().next().is_some(),
+            "--pattern requires at least one named capture group, e.g. `(?P<latency>[0-9.]+)`"
+        );
+        Ok(Self::Pattern(regex))
+    }
+
+    /// Returns the numeric fields of a line by name.
+    fn fields(&self, line: &str) -> Vec<(String, f64)> {
+        match self {
+            Self::Pattern(regex) => {
+                let Some(captures) = regex.captures(line) else { return Vec::new() };
+                regex
+                    .capture_names()
+                    .flatten()
+                    .filter_map(|name| {
+                        let value = captures.name(name)?.as_str().trim().parse().ok()?;
+                        Some((name.to_string(), value))
+                    })
+                    .collect()
+            }
+            Self::Logfmt => parse_logfmt(line)
+                .into_iter()
+                .filter_map(|(key, value)| Some((key, value.parse().ok()?)))
+                .collect(),
+        }
+    }
+}
+
+pub async fn open(
+    path: &Path,
+    send: &mpsc::Sender<Message>,
+    parser: LineParser,
+    follow: Option<Follow>,
+) -> Result<WorkerBuilder> {
+    let fd = fs::File::open(path).await.context("cannot open file for reading")?;
+    let mut send = send.clone();
+
+    Ok(Box::new(move |warnings, cancel| {
+        Box::pin(async move {
+            let mut read = super::thread_line_reader(fd, follow, cancel, warnings).await;
+
+            while let Some((line, time)) = read.recv().await {
+                for (name, value) in parser.fields(&line) {
+                    let message =
+                        Message { key: SeriesKey::new(name), value: Value::Number(value), time };
+                    send.feed(message).await?;
+                }
+                send.flush().await?;
+            }
+
+            Ok(())
+        })
+    }))
+}
+
+/// Parses `key=value` pairs separated by whitespace,
+/// where values may be quoted with `"` and keys without a value are skipped.
+fn parse_logfmt(line: &str) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.next_if(|char| char.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            return pairs;
+        }
+
+        let mut key = String::new();
+        while let Some(char) = chars.next_if(|&char| char != '=' && !char.is_whitespace()) {
+            key.push(char);
+        }
+        if chars.next_if_eq(&'=').is_none() {
+            continue;
+        }
+
+        let mut value = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            while let Some(char) = chars.next() {
+                match char {
+                    '"' => break,
+                    '\\' => value.extend(chars.next()),
+                    char => value.push(char),
+                }
+            }
+        } else {
+            while let Some(char) = chars.next_if(|char| !char.is_whitespace()) {
+                value.push(char);
+            }
+        }
+        pairs.push((key, value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_logfmt;
+
+    fn expected(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|&(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn parse_logfmt_pairs() {
+        assert_eq!(
+            parse_logfmt(r#"level=info dur=0.25 msg="request done""#),
+            expected(&[("level", "info"), ("dur", "0.25"), ("msg", "request done")]),
+        );
+        assert_eq!(parse_logfmt("  a=1\tb=2  "), expected(&[("a", "1"), ("b", "2")]));
+        assert_eq!(parse_logfmt(""), expected(&[]));
+    }
+
+    #[test]
+    fn parse_logfmt_quoted_values() {
+        assert_eq!(
+            parse_logfmt(r#"msg="say \"hi\"" path="C:\\tmp" n=1"#),
+            expected(&[("msg", r#"say "hi""#), ("path", r"C:\tmp"), ("n", "1")]),
+        );
+        // an unterminated quote extends to the end of the line
+        assert_eq!(parse_logfmt(r#"msg="no end n=1"#), expected(&[("msg", "no end n=1")]));
+    }
+
+    #[test]
+    fn parse_logfmt_skips_keys_without_values() {
+        assert_eq!(parse_logfmt("flag a=1 other"), expected(&[("a", "1")]));
+        assert_eq!(parse_logfmt("a= b=2"), expected(&[("a", ""), ("b", "2")]));
+    }
+}
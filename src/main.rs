@@ -1,11 +1,14 @@
 #![warn(clippy::pedantic)]
 #![allow(clippy::cast_possible_truncation, clippy::cast_precision_loss, clippy::cast_possible_wrap)]
 
+use std::env;
+
 use anyhow::{Context as _, Result};
 use clap::Parser as _;
 use flexi_logger::FileSpec;
 use tokio_util::sync::CancellationToken;
 
+mod config;
 mod input;
 mod options;
 mod ui;
@@ -13,7 +16,24 @@ pub mod util;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let options = options::Options::parse();
+    // the other options are only validated once the configuration file is merged in
+    let config_options = options::ConfigOptions::scan(env::args_os().skip(1));
+    let config_path = match config_options.config {
+        Some(path) => Some(path),
+        None if config_options.no_config => None,
+        None => config::default_path().filter(|path| path.exists()),
+    };
+    let (options, config) = match config_path {
+        Some(path) => {
+            let config = config::load(&path).with_context(|| format!("load {}", path.display()))?;
+            let mut args = env::args_os();
+            let options = options::Options::parse_from(
+                args.next().into_iter().chain(config.to_args()?).chain(args),
+            );
+            (options, config)
+        }
+        None => (options::Options::parse(), config::Config::default()),
+    };
     if options.log {
         flexi_logger::Logger::try_with_env()
             .context("parse RUST_LOG")?
@@ -29,8 +49,8 @@ async fn main() -> Result<()> {
         _ = ctrlc::set_handler(move || cancel.cancel()); // do not error out if ctrlc cannot be handled
     }
 
-    let input = options.inputs.open(&cancel).await?;
-    ui::run(options.ui, input, cancel.clone()).await?;
+    let input = options.inputs.open(&config.inputs, &cancel).await?;
+    ui::run(options.ui, config.series, &config.keys, input, cancel.clone()).await?;
     cancel.cancel();
 
     Ok(())
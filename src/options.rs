@@ -1,11 +1,24 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+
 use crate::{input, ui};
 
 #[derive(Debug, clap::Parser)]
+#[command(args_override_self = true)]
 pub struct Options {
     /// Write logs to current directory.
     #[arg(long)]
     pub log: bool,
 
+    /// Load settings from a TOML configuration file.
+    ///
+    /// Defaults to `$XDG_CONFIG_HOME/lpl/config.toml` if it exists.
+    #[arg(long)]
+    pub config:    Option<PathBuf>,
+    /// Do not load the default configuration file.
+    #[arg(long)]
+    pub no_config: bool,
+
     /// Input sources.
     #[command(flatten)]
     pub inputs: input::Options,
@@ -14,3 +27,34 @@ pub struct Options {
     #[command(flatten)]
     pub ui: ui::Options,
 }
+
+/// The options locating the configuration file, which are read before the other options,
+/// since the file may provide the values that they require.
+#[derive(Debug, Default)]
+pub struct ConfigOptions {
+    pub config:    Option<PathBuf>,
+    pub no_config: bool,
+}
+
+impl ConfigOptions {
+    /// Finds `--config` and `--no-config` in the command line arguments
+    /// without validating the other arguments.
+    pub fn scan(args: impl IntoIterator<Item = OsString>) -> Self {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.to_str() {
+                Some("--") => break,
+                Some("--config") => options.config = args.next().map(PathBuf::from),
+                Some("--no-config") => options.no_config = true,
+                Some(arg) => {
+                    if let Some(path) = arg.strip_prefix("--config=") {
+                        options.config = Some(PathBuf::from(path));
+                    }
+                }
+                None => {}
+            }
+        }
+        options
+    }
+}
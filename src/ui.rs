@@ -1,6 +1,7 @@
-use std::collections::VecDeque;
-use std::thread;
+use std::collections::{BTreeMap, VecDeque};
+use std::path::PathBuf;
 use std::time::{Duration, Instant, SystemTime};
+use std::{mem, thread};
 
 use anyhow::{Context as _, Result};
 use arcstr::ArcStr;
@@ -13,19 +14,36 @@ use ratatui::{layout, Terminal};
 use tokio::time;
 use tokio_util::sync::CancellationToken;
 
-use crate::input::{Input, WarningSender};
+use crate::input::{Input, Message, Opener, WarningSender};
 use crate::util;
 
+mod command;
+mod group;
+use group::{Aggregate, GroupPath, Grouping};
+mod keymap;
+pub use keymap::KeyConfig;
+use keymap::Keymap;
 mod layer_chart;
-use layer_chart::LayerChart;
+use layer_chart::{ChartCommand, LayerChart, MaxGap};
 mod layer_help;
 use layer_help::LayerHelp;
+mod layer_histogram;
+use layer_histogram::LayerHistogram;
+mod layer_inputs;
+use layer_inputs::LayerInputs;
 mod layer_legend;
-use layer_legend::LayerLegend;
+use layer_legend::{LayerLegend, LegendSort};
+mod layer_prompt;
+use layer_prompt::LayerPrompt;
 mod layer_warn;
 use layer_warn::LayerWarn;
+mod session;
+use session::{AggregateState, Session, TransformState};
+mod theme;
+use theme::{Theme, ThemeName};
 mod data;
-use data::Cache;
+pub use data::SeriesRule;
+use data::{Cache, ColorAssignment, ColorPool, DrawStyle, LabelPattern, Palette, SeriesFilter};
 
 #[derive(Debug, clap::Args)]
 #[group(id = "UI")]
@@ -40,9 +58,98 @@ pub struct Options {
     /// Duration in seconds to retain data for.
     #[arg(long, value_parser = |v: &str| v.parse::<f32>().map(Duration::from_secs_f32), default_value = "60")]
     data_backlog_duration: Duration,
+
+    /// Draw style for series with labels matching a pattern, in the form `PATTERN=STYLE`.
+    ///
+    /// `*` matches any substring and `?` matches any single character in `PATTERN`.
+    /// `STYLE` is one of `line`, `points`, `step`, `bars` and `stacked-area`.
+    #[arg(long, value_parser = parse_style_rule)]
+    style: Vec<(String, DrawStyle)>,
+
+    /// Break lines and stacked areas between samples further apart than this gap
+    /// instead of interpolating.
+    ///
+    /// Either a duration in seconds (e.g. `5`),
+    /// or a multiple of the median sample interval of the series (e.g. `3x`).
+    #[arg(long, value_parser = parse_max_gap)]
+    max_gap:    Option<MaxGap>,
+    /// Shade the regions of gaps detected by `--max-gap`.
+    #[arg(long)]
+    shade_gaps: bool,
+
+    /// Initial number of bins in the distribution view.
+    #[arg(long, default_value_t = 20)]
+    histogram_bins: usize,
+
+    /// Initial order of series in the legend.
+    #[arg(long, value_enum, default_value_t)]
+    legend_sort:       LegendSort,
+    /// Start with a compact legend that lists truncated labels without values.
+    #[arg(long)]
+    legend_compact:    bool,
+    /// List series without points in the time window in the legend.
+    #[arg(long)]
+    legend_show_empty: bool,
+
+    /// Group series in the legend by splitting labels with this separator, e.g. `.`.
+    ///
+    /// Groups can be collapsed and plotted as the sum or average of their series.
+    #[arg(long)]
+    group_separator: Option<String>,
+    /// Group series in the legend by metric name and then by the values of this tag.
+    ///
+    /// Series are tagged by labels in the form `name{key=value,...}`.
+    /// May be repeated to group by multiple tags in order.
+    #[arg(long, conflicts_with = "group_separator")]
+    group_by:        Vec<String>,
+
+    /// Start in X-Y mode, plotting other series against the series with this label.
+    #[arg(long)]
+    xy: Option<String>,
+
+    /// The palette of colors assigned to new series.
+    #[arg(long, value_enum, default_value_t)]
+    palette:          Palette,
+    /// How colors from the palette are assigned to new series.
+    #[arg(long, value_enum, default_value_t)]
+    color_assignment: ColorAssignment,
+
+    /// Color theme of the UI.
+    #[arg(long, value_enum, default_value_t)]
+    theme: ThemeName,
+
+    /// Restore the interactive UI state from this file if it exists, and save it on exit.
+    ///
+    /// The state can also be saved at any time with `S`.
+    #[arg(long)]
+    session: Option<PathBuf>,
+}
+
+fn parse_max_gap(arg: &str) -> Result<MaxGap> {
+    Ok(if let Some(multiple) = arg.strip_suffix('x') {
+        MaxGap::MedianMultiple(multiple.parse().context("parse median interval multiple")?)
+    } else {
+        MaxGap::Seconds(arg.parse().context("parse gap duration in seconds")?)
+    })
 }
 
-pub async fn run(options: Options, input: Input, cancel: CancellationToken) -> Result<()> {
+fn parse_style_rule(arg: &str) -> Result<(String, DrawStyle)> {
+    let (pattern, style) =
+        arg.split_once('=').context("--style argument should be in the form `PATTERN=STYLE`")?;
+    let style = <DrawStyle as clap::ValueEnum>::from_str(style, true)
+        .map_err(|err| anyhow::anyhow!("invalid style {style:?}: {err}"))?;
+    Ok((pattern.to_string(), style))
+}
+
+pub async fn run(
+    options: Options,
+    rules: Vec<SeriesRule>,
+    keys: &KeyConfig,
+    input: Input,
+    cancel: CancellationToken,
+) -> Result<()> {
+    let keymap = Keymap::new(keys).context("load key bindings")?;
+
     enable_raw_mode()?;
     let _raii = util::Finally(Some(((), |()| disable_raw_mode().context("disable raw mode"))));
 
@@ -50,7 +157,7 @@ pub async fn run(options: Options, input: Input, cancel: CancellationToken) -> R
     let mut terminal = Terminal::new(backend)?;
 
     crossterm::execute!(terminal.backend_mut(), terminal::EnterAlternateScreen)?;
-    let result = main_loop(options, cancel, &mut terminal, input).await;
+    let result = main_loop(options, rules, keymap, cancel, &mut terminal, input).await;
     crossterm::execute!(terminal.backend_mut(), terminal::LeaveAlternateScreen)
         .context("reset terminal")?;
     result?; // execute after resetting
@@ -65,6 +172,29 @@ struct Context {
     warning_sender:  WarningSender,
     cache:           Cache,
     current_targets: Option<Vec<layer_chart::DrawTarget>>,
+    /// The series used as the x axis in X-Y mode.
+    x_series:        Option<String>,
+
+    theme:  Theme,
+    keymap: Keymap,
+    opener: Opener,
+
+    /// How series are arranged in the legend.
+    grouping:   Grouping,
+    /// Collapsed groups plotted as a single aggregate line.
+    aggregates: BTreeMap<GroupPath, Aggregate>,
+
+    /// The legend search entered with `/`.
+    series_filter: Option<SeriesFilter>,
+
+    /// Number of bins in the distribution view, kept when switching series.
+    histogram_bins: usize,
+
+    /// Commands from the command line to be applied by `LayerChart` on the next render.
+    chart_commands: Vec<ChartCommand>,
+
+    /// Set by layers to save the session after handling the current event.
+    save_session_requested: bool,
 }
 
 #[portrait::make]
@@ -91,42 +221,144 @@ enum Layer {
     Base(LayerChart),
     Warn(LayerWarn),
     Help(LayerHelp),
+    Histogram(LayerHistogram),
+    Inputs(LayerInputs),
     Legend(LayerLegend),
+    Prompt(LayerPrompt),
 }
 
 enum LayerCommand {
     Insert(Layer, usize),
+    /// Push a layer on top of all existing layers.
+    Push(Layer),
     Remove,
 }
 
 async fn main_loop(
     options: Options,
+    config_rules: Vec<SeriesRule>,
+    keymap: Keymap,
     cancel: CancellationToken,
     terminal: &mut Terminal<impl Backend>,
-    Input { messages: mut input, warnings, warning_sender }: Input,
+    Input { messages: mut input, warnings, warning_sender, opener }: Input,
 ) -> Result<()> {
-    let mut events = {
-        let (send, recv) = mpsc::unbounded();
-        consume_events(cancel.clone(), send);
-        Some(recv)
+    // command line rules take precedence over rules from the config file
+    let rules = options
+        .style
+        .iter()
+        .map(|(pattern, style)| SeriesRule {
+            style: Some(*style),
+            ..SeriesRule::new(LabelPattern::Glob(pattern.clone()))
+        })
+        .chain(config_rules)
+        .collect();
+    let mut session = match &options.session {
+        Some(path) => {
+            Session::load(path).with_context(|| format!("load session {}", path.display()))?
+        }
+        None => Session::default(),
+    };
+    let color_pool =
+        ColorPool::new(options.palette, options.color_assignment, warning_sender.clone());
+    let cache = Cache::new(rules, mem::take(&mut session.series), color_pool);
+    let x_series = options.xy.clone().or(session.x_series);
+    let theme = options.theme;
+    let transform = session.transform.take().unwrap_or_default();
+    let histogram_bins = transform.histogram_bins.unwrap_or(options.histogram_bins).max(1);
+    // grouping from the command line replaces the saved grouping and its aggregates
+    let (grouping, aggregates) = if options.group_separator.is_some()
+        || !options.group_by.is_empty()
+    {
+        (Grouping::new(&options), BTreeMap::new())
+    } else {
+        let grouping = Grouping::from_parts(transform.group_separator, transform.group_by);
+        let aggregates =
+            transform.aggregates.into_iter().map(|state| (state.group, state.aggregate)).collect();
+        (grouping, aggregates)
     };
-
     let mut context = Context {
         options,
         cancel,
         warnings: VecDeque::new(),
         warning_sender,
-        cache: Cache::default(),
+        cache,
         current_targets: None,
+        x_series,
+        theme: Theme::new(theme),
+        keymap,
+        opener,
+        grouping,
+        aggregates,
+        series_filter: None,
+        histogram_bins,
+        chart_commands: Vec::new(),
+        save_session_requested: false,
     };
 
-    let mut warnings = Some(warnings);
+    let mut chart = LayerChart::new(&context.options);
+    if let Some(state) = &session.chart {
+        chart.restore_session(state);
+    }
+    let mut legend = LayerLegend::new(&context.options);
+    if let Some(state) = &session.legend {
+        legend.restore_session(&mut context, state);
+    }
+    let mut layers =
+        vec![Layer::Base(chart), Layer::Legend(legend), Layer::Warn(LayerWarn::default())];
+
+    run_layers(&mut context, terminal, &mut layers, &mut input, warnings).await?;
+
+    save_session(&context, &layers)
+}
+
+/// Saves the session to the path specified by `--session`, if any.
+fn save_session(context: &Context, layers: &[Layer]) -> Result<()> {
+    let Some(path) = &context.options.session else { return Ok(()) };
+
+    let mut session = Session {
+        series: context.cache.series_states(),
+        x_series: context.x_series.clone(),
+        transform: Some(TransformState {
+            group_separator: match &context.grouping {
+                Grouping::Separator(separator) => Some(separator.clone()),
+                _ => None,
+            },
+            group_by:        match &context.grouping {
+                Grouping::Tags(tag_keys) => tag_keys.clone(),
+                _ => Vec::new(),
+            },
+            aggregates:      context
+                .aggregates
+                .iter()
+                .map(|(group, &aggregate)| AggregateState { group: group.clone(), aggregate })
+                .collect(),
+            histogram_bins:  Some(context.histogram_bins),
+        }),
+        ..Session::default()
+    };
+    for layer in layers {
+        match layer {
+            Layer::Base(chart) => session.chart = Some(chart.session_state()),
+            Layer::Legend(legend) => session.legend = Some(legend.session_state(context)),
+            _ => {}
+        }
+    }
+    session.save(path).with_context(|| format!("save session {}", path.display()))
+}
 
-    let mut layers = vec![
-        Layer::Base(LayerChart::new(&context.options)),
-        Layer::Legend(LayerLegend::default()),
-        Layer::Warn(LayerWarn::default()),
-    ];
+async fn run_layers(
+    context: &mut Context,
+    terminal: &mut Terminal<impl Backend>,
+    layers: &mut Vec<Layer>,
+    input: &mut mpsc::Receiver<Message>,
+    warnings: mpsc::Receiver<(SystemTime, String)>,
+) -> Result<()> {
+    let mut events = {
+        let (send, recv) = mpsc::unbounded();
+        consume_events(context.cancel.clone(), send);
+        Some(recv)
+    };
+    let mut warnings = Some(warnings);
     let mut layer_cmds: Vec<LayerCommand> = Vec::new();
 
     let redraw_freq = Duration::from_millis(200);
@@ -137,8 +369,8 @@ async fn main_loop(
     loop {
         if redraw {
             let frame = terminal.draw(|frame| {
-                for layer in &mut layers {
-                    layer.render(&mut context, frame);
+                for layer in &mut *layers {
+                    layer.render(context, frame);
                 }
             })?;
             last_area = Some(frame.area);
@@ -149,7 +381,7 @@ async fn main_loop(
             event = util::some_or_pending(&mut events).fuse() => {
                 for i in (0..layers.len()).rev() {
                     let layer = layers.get_mut(i).unwrap();
-                    let flow = layer.handle_input(&mut context, &event, &mut layer_cmds, last_area.expect("redraw was true the first time"))?;
+                    let flow = layer.handle_input(context, &event, &mut layer_cmds, last_area.expect("redraw was true the first time"))?;
 
                     let mut removed = false;
                     for cmd in layer_cmds.drain(..) {
@@ -157,6 +389,7 @@ async fn main_loop(
                             LayerCommand::Insert(new_layer, offset) => {
                                 layers.insert(i + 1 + offset, new_layer);
                             },
+                            LayerCommand::Push(new_layer) => layers.push(new_layer),
                             LayerCommand::Remove => {
                                 assert!(i > 0);
                                 assert!(!removed, "cannot remove twice");
@@ -170,6 +403,13 @@ async fn main_loop(
                         break;
                     }
                 }
+
+                if mem::take(&mut context.save_session_requested) {
+                    match save_session(context, layers) {
+                        Ok(()) => context.warning_sender.send("Session saved"),
+                        Err(err) => context.warning_sender.send(format!("Error: {err:?}")),
+                    }
+                }
                 true
             },
             message = input.next() => {
@@ -0,0 +1,158 @@
+use std::path::PathBuf;
+
+use anyhow::{Context as _, Result};
+
+use super::data::DrawStyle;
+use super::group::Grouping;
+use super::layer_chart::ChartCommand;
+use super::Context;
+use crate::{input, util};
+
+/// Commands accepted by the command line, with their argument syntax.
+pub const COMMANDS: &[(&str, &str)] = &[
+    ("hide", "PATTERN..."),
+    ("show", "PATTERN..."),
+    ("color", "PATTERN #RRGGBB"),
+    ("style", "PATTERN STYLE"),
+    ("window", "DURATION"),
+    ("ylog", ""),
+    ("export", "PATH.svg"),
+    ("add-input", "--json PATH | --csv PATH | ..."),
+    ("group-by", "[TAG...]"),
+];
+
+/// Runs a command line such as `hide cpu*`.
+pub fn run(context: &mut Context, line: &str) -> Result<()> {
+    let words = shell_words::split(line).context("parse command line")?;
+    let words: Vec<&str> = words.iter().map(String::as_str).collect();
+    let Some((&command, args)) = words.split_first() else { return Ok(()) };
+
+    match (command, args) {
+        ("hide" | "show", [_, ..]) => {
+            for &pattern in args {
+                for config in matching_configs(context, pattern)? {
+                    config.visible = command == "show";
+                }
+            }
+        }
+        ("color", &[pattern, color]) => {
+            let color = util::parse_color(color)?;
+            for config in matching_configs(context, pattern)? {
+                config.color = color;
+            }
+        }
+        ("style", &[pattern, style]) => {
+            let style = <DrawStyle as clap::ValueEnum>::from_str(style, true)
+                .map_err(|err| anyhow::anyhow!("invalid style {style:?}: {err}"))?;
+            for config in matching_configs(context, pattern)? {
+                config.style = style;
+            }
+        }
+        ("window", &[duration]) => {
+            let duration = util::parse_duration(duration)?;
+            context.chart_commands.push(ChartCommand::Window(duration));
+        }
+        ("ylog", []) => context.chart_commands.push(ChartCommand::ToggleYLog),
+        ("export", &[path]) => {
+            context.chart_commands.push(ChartCommand::Export(PathBuf::from(path)));
+        }
+        ("add-input", [_, ..]) => {
+            let args: Vec<String> = args.iter().map(|&arg| arg.to_string()).collect();
+            open_inputs(context, input::parse_specs(&args)?);
+        }
+        ("group-by", []) => set_grouping(context, Grouping::new(&context.options)),
+        ("group-by", [_, ..]) => {
+            let tags = args.iter().map(|&tag| tag.to_string()).collect();
+            set_grouping(context, Grouping::Tags(tags));
+        }
+        _ => {
+            let (_, syntax) = COMMANDS
+                .iter()
+                .find(|&&(name, _)| name == command)
+                .with_context(|| format!("unknown command {command:?}"))?;
+            anyhow::bail!("usage: :{command} {syntax}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Opens inputs in the background, reporting failures as warnings.
+pub fn open_inputs(context: &Context, specs: Vec<input::Spec>) {
+    let opener = context.opener.clone();
+    let mut warnings = context.warning_sender.clone();
+    tokio::spawn(async move {
+        for spec in specs {
+            if let Err(err) = opener.open(&spec).await {
+                warnings.send(format!("Error: {err:?}"));
+            }
+        }
+    });
+}
+
+fn set_grouping(context: &mut Context, grouping: Grouping) {
+    context.grouping = grouping;
+    context.aggregates.clear();
+}
+
+fn matching_configs<'t>(
+    context: &'t mut Context,
+    pattern: &str,
+) -> Result<Vec<&'t mut super::data::DisplayConfig>> {
+    let configs: Vec<_> = context
+        .cache
+        .disp_config
+        .iter_mut()
+        .filter(|(label, _)| util::glob_match(pattern, label))
+        .map(|(_, config)| config)
+        .collect();
+    anyhow::ensure!(!configs.is_empty(), "no series matches {pattern:?}");
+    Ok(configs)
+}
+
+/// Completes the last word of `line`,
+/// returning the completed line and the candidates if the completion is ambiguous.
+pub fn complete(context: &Context, line: &str) -> (String, Vec<String>) {
+    let word_start = line.rfind(char::is_whitespace).map_or(0, |index| index + 1);
+    let (head, prefix) = line.split_at(word_start);
+    let preceding: Vec<&str> = head.split_whitespace().collect();
+
+    let candidates: Vec<String> = match preceding.as_slice() {
+        [] => COMMANDS.iter().map(|&(name, _)| name.to_string()).collect(),
+        ["hide" | "show", ..] | ["color" | "style"] => {
+            context.cache.disp_config.keys().cloned().collect()
+        }
+        ["group-by", ..] => {
+            let mut tags: Vec<_> =
+                context.cache.keys.values().flat_map(|key| key.tags.keys()).cloned().collect();
+            tags.sort();
+            tags.dedup();
+            tags
+        }
+        ["style", _] => <DrawStyle as clap::ValueEnum>::value_variants()
+            .iter()
+            .filter_map(clap::ValueEnum::to_possible_value)
+            .map(|value| value.get_name().to_string())
+            .collect(),
+        _ => Vec::new(),
+    };
+    let candidates: Vec<String> =
+        candidates.into_iter().filter(|candidate| candidate.starts_with(prefix)).collect();
+
+    match candidates.as_slice() {
+        [] => (line.to_string(), Vec::new()),
+        [candidate] => (format!("{head}{candidate} "), Vec::new()),
+        [first, rest @ ..] => {
+            let common_len = rest.iter().fold(first.len(), |len, candidate| {
+                first
+                    .char_indices()
+                    .zip(candidate.chars())
+                    .take_while(|&((index, a), b)| index < len && a == b)
+                    .map(|((index, a), _)| index + a.len_utf8())
+                    .last()
+                    .unwrap_or(0)
+            });
+            (format!("{head}{}", &first[..common_len]), candidates)
+        }
+    }
+}
@@ -1,29 +1,272 @@
 use std::collections::{BTreeMap, VecDeque};
 use std::time::SystemTime;
 
-use crate::input;
+use serde::{de, Deserialize, Serialize};
+
+use super::session::SeriesState;
+use crate::input::{SeriesKey, WarningSender};
+use crate::{input, util};
 
-#[derive(Default)]
 pub struct Cache {
     pub data:        Freezable,
     pub disp_config: BTreeMap<String, DisplayConfig>,
+    /// The metric name and tags of each series by label.
+    pub keys:        BTreeMap<String, SeriesKey>,
     color_pool:      ColorPool,
+    rules:           Vec<SeriesRule>,
+    /// Display settings restored from a session, applied to new series with the same label.
+    restored:        BTreeMap<String, SeriesState>,
 }
 
 pub struct DisplayConfig {
-    pub visible: bool,
-    pub color:   [u8; 3],
+    pub visible:    bool,
+    pub color:      [u8; 3],
+    pub style:      DrawStyle,
+    pub axis:       Axis,
+    pub thresholds: Vec<f64>,
+}
+
+/// Display settings applied to new series with matching labels.
+///
+/// For each setting, the first matching rule that specifies it takes effect.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct SeriesRule {
+    #[serde(rename = "match")]
+    pub pattern:    LabelPattern,
+    #[serde(default, deserialize_with = "deserialize_color")]
+    pub color:      Option<[u8; 3]>,
+    pub visible:    Option<bool>,
+    pub style:      Option<DrawStyle>,
+    pub axis:       Option<Axis>,
+    /// Values at which horizontal lines are drawn.
+    pub thresholds: Option<Vec<f64>>,
+}
+
+impl SeriesRule {
+    pub fn new(pattern: LabelPattern) -> Self {
+        Self { pattern, color: None, visible: None, style: None, axis: None, thresholds: None }
+    }
+}
+
+fn deserialize_color<'de, D: de::Deserializer<'de>>(d: D) -> Result<Option<[u8; 3]>, D::Error> {
+    let color = String::deserialize(d)?;
+    util::parse_color(&color).map(Some).map_err(de::Error::custom)
+}
+
+pub enum LabelPattern {
+    /// A wildcard pattern matching the whole label.
+    Glob(String),
+    /// A regular expression matching any part of the label.
+    Regex(regex::Regex),
+}
+
+impl LabelPattern {
+    pub fn matches(&self, label: &str) -> bool {
+        match self {
+            Self::Glob(pattern) => util::glob_match(pattern, label),
+            Self::Regex(regex) => regex.is_match(label),
+        }
+    }
+}
+
+/// An incremental search over series labels entered with `/` in the legend.
+///
+/// The filter is a case-insensitive fuzzy match,
+/// i.e. the characters must appear in the label in order but not necessarily contiguously,
+/// or a regular expression if it starts with `~`,
+/// or a list of tags in the form `key=PATTERN,...` that the series must all have.
+pub struct SeriesFilter {
+    pub text: String,
+    matcher:  FilterMatcher,
+}
+
+enum FilterMatcher {
+    Fuzzy(Vec<char>),
+    Regex(regex::Regex),
+    InvalidRegex,
+    /// Tag keys and glob patterns of their values.
+    Tags(Vec<(String, String)>),
+}
+
+impl SeriesFilter {
+    pub fn new(text: String) -> Self {
+        let matcher = match text.strip_prefix('~') {
+            Some(pattern) => {
+                regex::Regex::new(pattern).map_or(FilterMatcher::InvalidRegex, FilterMatcher::Regex)
+            }
+            None => match parse_tag_filter(&text) {
+                Some(tags) => FilterMatcher::Tags(tags),
+                None => FilterMatcher::Fuzzy(text.chars().flat_map(char::to_lowercase).collect()),
+            },
+        };
+        Self { text, matcher }
+    }
+
+    pub fn is_valid(&self) -> bool { !matches!(self.matcher, FilterMatcher::InvalidRegex) }
+
+    /// Whether the series `label` matches, where `key` holds its tags if it has been received.
+    pub fn matches(&self, label: &str, key: Option<&SeriesKey>) -> bool {
+        match &self.matcher {
+            FilterMatcher::Fuzzy(chars) => {
+                let mut label_chars = label.chars().flat_map(char::to_lowercase);
+                chars.iter().all(|&char| label_chars.any(|label_char| label_char == char))
+            }
+            FilterMatcher::Regex(regex) => regex.is_match(label),
+            FilterMatcher::InvalidRegex => false,
+            FilterMatcher::Tags(patterns) => key.is_some_and(|key| {
+                patterns.iter().all(|(tag_key, pattern)| {
+                    key.tags.get(tag_key).is_some_and(|value| util::glob_match(pattern, value))
+                })
+            }),
+        }
+    }
+}
+
+fn parse_tag_filter(text: &str) -> Option<Vec<(String, String)>> {
+    text.split(',')
+        .map(|tag| {
+            let (key, pattern) = tag.split_once('=')?;
+            let key = key.trim();
+            (!key.is_empty() && !key.contains(char::is_whitespace))
+                .then(|| (key.to_string(), pattern.trim().to_string()))
+        })
+        .collect()
+}
+
+impl<'de> Deserialize<'de> for LabelPattern {
+    fn deserialize<D: de::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(d)?;
+        regex::Regex::new(&pattern).map(Self::Regex).map_err(de::Error::custom)
+    }
+}
+
+/// The y axis that a series is plotted against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Axis {
+    #[default]
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DrawStyle {
+    /// Straight lines between consecutive samples.
+    #[default]
+    Line,
+    /// Scatter points without connecting lines.
+    Points,
+    /// Hold each value until the next sample.
+    Step,
+    /// Bars spanning the interval since the previous sample.
+    Bars,
+    /// Filled area stacked on top of other stacked series.
+    StackedArea,
+}
+
+impl DrawStyle {
+    #[must_use]
+    pub fn next(self) -> Self {
+        match self {
+            Self::Line => Self::Points,
+            Self::Points => Self::Step,
+            Self::Step => Self::Bars,
+            Self::Bars => Self::StackedArea,
+            Self::StackedArea => Self::Line,
+        }
+    }
 }
 
 impl Cache {
+    pub fn new(
+        rules: Vec<SeriesRule>,
+        restored: BTreeMap<String, SeriesState>,
+        color_pool: ColorPool,
+    ) -> Self {
+        Self {
+            data: Freezable::default(),
+            disp_config: BTreeMap::new(),
+            keys: BTreeMap::new(),
+            color_pool,
+            rules,
+            restored,
+        }
+    }
+
+    fn new_display_config(&mut self, label: &str) -> DisplayConfig {
+        let rules: Vec<_> = self.rules.iter().filter(|rule| rule.pattern.matches(label)).collect();
+        let thresholds = rules.iter().find_map(|rule| rule.thresholds.clone()).unwrap_or_default();
+
+        // interactive changes from the previous session take precedence over rules
+        if let Some(state) = self.restored.get(label) {
+            return DisplayConfig {
+                visible: state.visible,
+                color: state.color,
+                style: state.style,
+                axis: state.axis,
+                thresholds,
+            };
+        }
+
+        let color = rules.iter().find_map(|rule| rule.color);
+        DisplayConfig {
+            visible: rules.iter().find_map(|rule| rule.visible).unwrap_or(true),
+            color: color.unwrap_or_else(|| self.color_pool.next(label)),
+            style: rules.iter().find_map(|rule| rule.style).unwrap_or_default(),
+            axis: rules.iter().find_map(|rule| rule.axis).unwrap_or_default(),
+            thresholds,
+        }
+    }
+
+    /// Returns the display settings of all series to be saved in a session,
+    /// including restored series that have not appeared in this run.
+    pub fn series_states(&self) -> BTreeMap<String, SeriesState> {
+        let mut states = self.restored.clone();
+        states.extend(self.disp_config.iter().map(|(label, config)| {
+            let state = SeriesState {
+                color:   config.color,
+                visible: config.visible,
+                style:   config.style,
+                axis:    config.axis,
+            };
+            (label.clone(), state)
+        }));
+        states
+    }
+
     pub fn push_message(&mut self, message: input::Message) {
-        self.disp_config
-            .entry(message.label.clone())
-            .or_insert_with(|| DisplayConfig { visible: true, color: self.color_pool.next() });
+        let value = match message.value {
+            input::Value::Number(value) => value,
+            input::Value::Annotation(text) => {
+                self.push_annotation(message.time, text);
+                return;
+            }
+        };
+
+        let label = message.key.label();
+        if !self.disp_config.contains_key(&label) {
+            let config = self.new_display_config(&label);
+            self.disp_config.insert(label.clone(), config);
+            self.color_pool.series_added(self.disp_config.len());
+            self.keys.insert(label.clone(), message.key);
+        }
 
-        let series =
-            self.data.map.entry(message.label).or_insert_with(|| Series { data: VecDeque::new() });
-        series.data.push_back(Datum { time: message.time, value: message.value });
+        let series = self.data.map.entry(label).or_insert_with(|| Series { data: VecDeque::new() });
+        // inputs with timestamps from the data may deliver samples out of order
+        let index = series.data.partition_point(|datum| datum.time <= message.time);
+        series.data.insert(index, Datum { time: message.time, value });
+    }
+
+    /// Returns the metric name and tags of the series `label`,
+    /// or `None` if the label is not of a received series, e.g. of an aggregate.
+    pub fn key(&self, label: &str) -> Option<&SeriesKey> { self.keys.get(label) }
+
+    pub fn push_annotation(&mut self, time: SystemTime, text: String) {
+        let annotations = &mut self.data.annotations;
+        let index = annotations.partition_point(|annotation| annotation.time <= time);
+        annotations.insert(index, Annotation { time, text });
     }
 
     pub fn trim(&mut self, epoch: SystemTime) {
@@ -33,12 +276,16 @@ impl Cache {
         }
 
         self.data.map.retain(|_, series| !series.data.is_empty());
+
+        let par_pt = self.data.annotations.partition_point(|annotation| annotation.time < epoch);
+        self.data.annotations.drain(..par_pt);
     }
 }
 
 #[derive(Default, Clone)]
 pub struct Freezable {
-    pub map: BTreeMap<String, Series>,
+    pub map:         BTreeMap<String, Series>,
+    pub annotations: VecDeque<Annotation>,
 }
 
 #[derive(Clone)]
@@ -52,29 +299,136 @@ pub struct Datum {
     pub value: f64,
 }
 
-const DEFAULT_COLOR_MAP: &[[u8; 3]] = &[
-    // Source: Set1 from matplotlib
-    [228, 26, 28],
-    [55, 126, 184],
-    [77, 175, 74],
-    [152, 78, 163],
-    [255, 127, 0],
-    [255, 255, 51],
-    [166, 86, 40],
-    [247, 129, 191],
-    [153, 153, 153],
-];
-
-#[derive(Default)]
-struct ColorPool {
+#[derive(Clone)]
+pub struct Annotation {
+    pub time: SystemTime,
+    pub text: String,
+}
+
+/// A set of colors assigned to new series.
+#[derive(Debug, Clone, Copy, Default, clap::ValueEnum)]
+pub enum Palette {
+    /// Set1 from matplotlib.
+    #[default]
+    Set1,
+    /// The default palette of Tableau 10.
+    Tableau10,
+    /// The colorblind-safe palette by Okabe and Ito.
+    OkabeIto,
+    /// Colors sampled evenly from the viridis colormap.
+    Viridis,
+}
+
+impl Palette {
+    fn colors(self) -> &'static [[u8; 3]] {
+        match self {
+            Self::Set1 => &[
+                [228, 26, 28],
+                [55, 126, 184],
+                [77, 175, 74],
+                [152, 78, 163],
+                [255, 127, 0],
+                [255, 255, 51],
+                [166, 86, 40],
+                [247, 129, 191],
+                [153, 153, 153],
+            ],
+            Self::Tableau10 => &[
+                [78, 121, 167],
+                [242, 142, 43],
+                [225, 87, 89],
+                [118, 183, 178],
+                [89, 161, 79],
+                [237, 201, 72],
+                [176, 122, 161],
+                [255, 157, 167],
+                [156, 117, 95],
+                [186, 176, 172],
+            ],
+            Self::OkabeIto => &[
+                [230, 159, 0],
+                [86, 180, 233],
+                [0, 158, 115],
+                [240, 228, 66],
+                [0, 114, 178],
+                [213, 94, 0],
+                [204, 121, 167],
+                // the original black is replaced with grey to remain visible on dark terminals
+                [153, 153, 153],
+            ],
+            Self::Viridis => &[
+                [68, 1, 84],
+                [70, 51, 126],
+                [54, 92, 141],
+                [39, 127, 142],
+                [31, 161, 135],
+                [74, 193, 109],
+                [160, 218, 57],
+                [253, 231, 37],
+            ],
+        }
+    }
+}
+
+/// How colors from the palette are assigned to new series.
+#[derive(Debug, Clone, Copy, Default, clap::ValueEnum)]
+pub enum ColorAssignment {
+    /// Pick a color from the hash of the label, so that a series gets the same color across runs.
+    ///
+    /// The color only depends on the label, so series whose labels hash to the same color share it.
+    #[default]
+    Hash,
+    /// Cycle through the palette in the order that series first appear.
+    Arrival,
+}
+
+pub struct ColorPool {
+    palette:    Palette,
+    assignment: ColorAssignment,
     next_color: usize,
+    warnings:   WarningSender,
 }
 
 impl ColorPool {
-    fn next(&mut self) -> [u8; 3] {
-        let offset = self.next_color;
-        self.next_color += 1;
-        self.next_color %= DEFAULT_COLOR_MAP.len();
-        DEFAULT_COLOR_MAP[offset]
+    pub fn new(palette: Palette, assignment: ColorAssignment, warnings: WarningSender) -> Self {
+        Self { palette, assignment, next_color: 0, warnings }
     }
+
+    fn next(&mut self, label: &str) -> [u8; 3] {
+        let colors = self.palette.colors();
+
+        let offset = match self.assignment {
+            ColorAssignment::Hash => (label_hash(label) % colors.len() as u64) as usize,
+            ColorAssignment::Arrival => {
+                let offset = self.next_color;
+                self.next_color += 1;
+                self.next_color %= colors.len();
+                offset
+            }
+        };
+
+        colors[offset]
+    }
+
+    /// Warns once the number of series, including those colored by rules or sessions,
+    /// exceeds the number of colors in the palette.
+    fn series_added(&mut self, series: usize) {
+        let colors = self.palette.colors().len();
+        if series == colors + 1 {
+            let palette = clap::ValueEnum::to_possible_value(&self.palette)
+                .expect("palettes are not skipped");
+            self.warnings.send(format!(
+                "There are more series than the {colors} colors of the {} palette, some colors \
+                 will be reused",
+                palette.get_name(),
+            ));
+        }
+    }
+}
+
+/// FNV-1a hash of the label, which unlike `std::hash` is stable across builds.
+fn label_hash(label: &str) -> u64 {
+    label.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
 }
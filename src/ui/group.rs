@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::data::{Cache, DrawStyle};
+use super::layer_chart::DrawTarget;
+use super::Options;
+use crate::input::{self, SeriesKey};
+
+/// How series are arranged into a tree of groups in the legend.
+pub enum Grouping {
+    Flat,
+    /// Split labels by a separator, e.g. `node1.cpu.user` is in the groups `node1` and `cpu`.
+    Separator(String),
+    /// Group by metric name and then by the values of these tags,
+    /// e.g. `requests{host=a,status=200}` is in the groups `requests` and `status=200`
+    /// when grouped by `status`.
+    Tags(Vec<String>),
+}
+
+/// The names of the nested groups containing a series, from the outermost.
+pub type GroupPath = Vec<String>;
+
+impl Grouping {
+    /// Returns the grouping specified by `--group-separator` or `--group-by`.
+    pub fn new(options: &Options) -> Self {
+        Self::from_parts(options.group_separator.clone(), options.group_by.clone())
+    }
+
+    /// Groups by `separator` if any, otherwise by `tag_keys` if not empty.
+    pub fn from_parts(separator: Option<String>, tag_keys: Vec<String>) -> Self {
+        if let Some(separator) = separator {
+            Self::Separator(separator)
+        } else if !tag_keys.is_empty() {
+            Self::Tags(tag_keys)
+        } else {
+            Self::Flat
+        }
+    }
+
+    /// Returns the names of the groups containing the series `label` from the outermost,
+    /// and the name of the series within its innermost group.
+    pub fn path(&self, cache: &Cache, label: &str) -> (GroupPath, String) {
+        match self {
+            Self::Flat => (Vec::new(), label.to_string()),
+            Self::Separator(separator) => {
+                let mut groups: Vec<_> =
+                    label.split(separator.as_str()).map(String::from).collect();
+                let name = groups.pop().expect("split yields at least one item");
+                (groups, name)
+            }
+            Self::Tags(tag_keys) => {
+                let SeriesKey { name, mut tags } =
+                    cache.key(label).cloned().unwrap_or_else(|| SeriesKey::new(label.to_string()));
+                let mut groups = vec![name.clone()];
+                for tag_key in tag_keys {
+                    if let Some(value) = tags.remove(tag_key) {
+                        groups.push(input::format_tag(tag_key, &value));
+                    }
+                }
+                let rest = SeriesKey { name, tags }.label();
+                match rest.find('{') {
+                    Some(start) => (groups, rest[start..].to_string()),
+                    None => (groups, rest),
+                }
+            }
+        }
+    }
+
+    /// Whether the series `label` is a descendant of the group at `group`.
+    pub fn contains(&self, cache: &Cache, group: &[String], label: &str) -> bool {
+        let (groups, _) = self.path(cache, label);
+        groups.starts_with(group)
+    }
+
+    /// Formats the path of a group in the same form as labels.
+    pub fn display(&self, group: &[String]) -> String {
+        match self {
+            Self::Flat => String::new(),
+            Self::Separator(separator) => group.join(separator),
+            Self::Tags(_) => match group.split_first() {
+                Some((name, [])) => name.clone(),
+                Some((name, tags)) => format!("{name}{{{}}}", tags.join(",")),
+                None => String::new(),
+            },
+        }
+    }
+}
+
+/// How the series in a collapsed group are combined into a single line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Aggregate {
+    Sum,
+    Avg,
+}
+
+impl Aggregate {
+    /// Cycles through no aggregation, sum and average.
+    pub fn next(current: Option<Self>) -> Option<Self> {
+        match current {
+            None => Some(Self::Sum),
+            Some(Self::Sum) => Some(Self::Avg),
+            Some(Self::Avg) => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sum => "sum",
+            Self::Avg => "avg",
+        }
+    }
+}
+
+/// Replaces the visible members of each aggregated group in `targets`
+/// with a single target plotting their aggregate.
+///
+/// Members of the group are hidden so that only the aggregate line is drawn.
+pub fn aggregate_targets(
+    targets: &mut Vec<DrawTarget>,
+    cache: &Cache,
+    grouping: &Grouping,
+    aggregates: &BTreeMap<GroupPath, Aggregate>,
+) {
+    for (group, &aggregate) in aggregates {
+        let mut members: Vec<_> = targets
+            .iter_mut()
+            .filter(|target| {
+                target.group.is_none() && grouping.contains(cache, group, &target.label)
+            })
+            .collect();
+        let Some(first) = members.iter().find(|target| target.visible) else { continue };
+
+        let color = first.color;
+        let axis = first.axis;
+        let visible_points: Vec<_> = members
+            .iter()
+            .filter(|target| target.visible)
+            .map(|target| target.points.as_slice())
+            .collect();
+        let points = aggregate_points(&visible_points, aggregate);
+        for member in &mut members {
+            member.visible = false;
+        }
+
+        targets.push(DrawTarget {
+            points,
+            visible: true,
+            color,
+            style: DrawStyle::Line,
+            axis,
+            thresholds: Vec::new(),
+            label: format!("{} ({})", grouping.display(group), aggregate.name()),
+            group: Some(group.clone()),
+        });
+    }
+}
+
+/// Combines series sampled at different times,
+/// where each series holds its latest value until its next sample.
+///
+/// A point is produced at every sample time of any series,
+/// aggregating the series that have started by then.
+fn aggregate_points(series: &[&[(f64, f64)]], aggregate: Aggregate) -> Vec<(f64, f64)> {
+    let mut xs: Vec<f64> =
+        series.iter().flat_map(|points| points.iter().map(|&(x, _)| x)).collect();
+    xs.sort_by(f64::total_cmp);
+    xs.dedup();
+
+    let mut cursors = vec![0; series.len()];
+    xs.into_iter()
+        .map(|x| {
+            let (mut sum, mut count) = (0., 0_u32);
+            for (points, cursor) in series.iter().zip(&mut cursors) {
+                while points.get(*cursor).is_some_and(|&(next_x, _)| next_x <= x) {
+                    *cursor += 1;
+                }
+                if let Some(&(_, y)) = cursor.checked_sub(1).and_then(|index| points.get(index)) {
+                    sum += y;
+                    count += 1;
+                }
+            }
+            match aggregate {
+                Aggregate::Sum => (x, sum),
+                Aggregate::Avg => (x, sum / f64::from(count)),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{aggregate_points, Aggregate};
+
+    #[test]
+    fn aggregate_holds_latest_values() {
+        let a = [(0., 1.), (2., 3.)];
+        let b = [(1., 10.), (2., 20.), (3., 30.)];
+        assert_eq!(
+            aggregate_points(&[&a, &b], Aggregate::Sum),
+            [(0., 1.), (1., 11.), (2., 23.), (3., 33.)],
+        );
+        assert_eq!(
+            aggregate_points(&[&a, &b], Aggregate::Avg),
+            [(0., 1.), (1., 5.5), (2., 11.5), (3., 16.5)],
+        );
+    }
+
+    #[test]
+    fn aggregate_empty() {
+        assert_eq!(aggregate_points(&[], Aggregate::Sum), []);
+        assert_eq!(aggregate_points(&[&[], &[(1., 2.)]], Aggregate::Avg), [(1., 2.)]);
+    }
+
+    #[test]
+    fn cycle_aggregates() {
+        assert_eq!(Aggregate::next(None), Some(Aggregate::Sum));
+        assert_eq!(Aggregate::next(Some(Aggregate::Sum)), Some(Aggregate::Avg));
+        assert_eq!(Aggregate::next(Some(Aggregate::Avg)), None);
+    }
+}
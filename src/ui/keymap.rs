@@ -0,0 +1,385 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use anyhow::{Context as _, Result};
+use crossterm::event::{Event, KeyCode, KeyEvent};
+use serde::Deserialize;
+
+/// A group of key bindings that are active in the same context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Scope {
+    Help,
+    Main,
+    Prompt,
+    Distribution,
+    Warnings,
+    Legend,
+    /// Keys following [`Action::ChangeColor`] in the legend.
+    LegendColor,
+    Inputs,
+}
+
+impl Scope {
+    const ALL: &[Self] = &[
+        Self::Help,
+        Self::Main,
+        Self::Prompt,
+        Self::Distribution,
+        Self::Warnings,
+        Self::Legend,
+        Self::LegendColor,
+        Self::Inputs,
+    ];
+
+    /// The name of the scope in the `[keys]` section of the config file.
+    fn name(self) -> &'static str {
+        match self {
+            Self::Help => "help",
+            Self::Main => "main",
+            Self::Prompt => "prompt",
+            Self::Distribution => "distribution",
+            Self::Warnings => "warnings",
+            Self::Legend => "legend",
+            Self::LegendColor => "legend-color",
+            Self::Inputs => "inputs",
+        }
+    }
+
+    fn title(self, keymap: &Keymap) -> String {
+        match self {
+            Self::Help => "Help".into(),
+            Self::Main => "Main".into(),
+            Self::Prompt => "Prompt".into(),
+            Self::Distribution => "Distribution".into(),
+            Self::Warnings => "Warnings".into(),
+            Self::Legend => "Legend".into(),
+            Self::LegendColor => {
+                format!("Legend color (after {})", keymap.keys_display(Action::ChangeColor))
+            }
+            Self::Inputs => "Inputs".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    CloseHelp,
+
+    Quit,
+    ShowHelp,
+    Freeze,
+    ZoomOut,
+    ZoomIn,
+    PanLeft,
+    PanLeftFar,
+    PanRight,
+    PanRightFar,
+    ResetView,
+    AddMarker,
+    ToggleHeatmap,
+    SaveSession,
+    CommandLine,
+    ShowInputs,
+
+    Submit,
+    Cancel,
+    Complete,
+
+    CloseDistribution,
+    FewerBins,
+    MoreBins,
+
+    FocusWarnings,
+    ScrollDown,
+    ScrollUp,
+    ScrollOldest,
+    ScrollLatest,
+    ZoomWarnings,
+    FreezeWarnings,
+
+    FocusLegend,
+    MoveLegendLeft,
+    MoveLegendRight,
+    MoveLegendUp,
+    MoveLegendDown,
+    PreviousSeries,
+    NextSeries,
+    ToggleVisibility,
+    CycleStyle,
+    ToggleXAxis,
+    ShowDistribution,
+    ChangeColor,
+    Search,
+    ShowMatching,
+    HideMatching,
+    Solo,
+    CycleSort,
+    ToggleCompact,
+    ToggleEmpty,
+    ToggleGroup,
+    CycleAggregate,
+
+    MoreRed,
+    LessRed,
+    MoreGreen,
+    LessGreen,
+    MoreBlue,
+    LessBlue,
+    RandomColor,
+
+    CloseInputs,
+    PreviousInput,
+    NextInput,
+    TogglePause,
+    StopInput,
+    AddInput,
+    RunCommand,
+}
+
+struct Binding {
+    scope:        Scope,
+    action:       Action,
+    /// The name of the action in the `[keys]` section of the config file.
+    name:         &'static str,
+    description:  &'static str,
+    default_keys: &'static [&'static str],
+}
+
+const fn bind(
+    scope: Scope,
+    action: Action,
+    name: &'static str,
+    description: &'static str,
+    default_keys: &'static [&'static str],
+) -> Binding {
+    Binding { scope, action, name, description, default_keys }
+}
+
+#[rustfmt::skip]
+const BINDINGS: &[Binding] = &[
+    bind(Scope::Help, Action::CloseHelp, "close", "Close this menu", &["q"]),
+
+    bind(Scope::Main, Action::ShowHelp, "help", "Display this menu", &["?"]),
+    bind(Scope::Main, Action::Quit, "quit", "Exit the application", &["q"]),
+    bind(Scope::Main, Action::Freeze, "freeze", "Pause data", &["SPACE"]),
+    bind(Scope::Main, Action::ZoomOut, "zoom-out", "Zoom out (0.8x)", &["-"]),
+    bind(Scope::Main, Action::ZoomIn, "zoom-in", "Zoom in (1.25x)", &["="]),
+    bind(Scope::Main, Action::PanLeft, "pan-left", "Move viewport leftwards by 10%", &["h"]),
+    bind(Scope::Main, Action::PanLeftFar, "pan-left-far", "Move viewport leftwards by 50%", &["H"]),
+    bind(Scope::Main, Action::PanRight, "pan-right", "Move viewport rightwards by 10%", &["l"]),
+    bind(Scope::Main, Action::PanRightFar, "pan-right-far", "Move viewport rightwards by 50%", &["L"]),
+    bind(Scope::Main, Action::ResetView, "reset-view", "Reset viewport to the full backlog range", &["r"]),
+    bind(Scope::Main, Action::AddMarker, "add-marker", "Add an annotation marker at the current time", &["m"]),
+    bind(Scope::Main, Action::ToggleHeatmap, "toggle-heatmap", "Toggle heatmap view", &["v"]),
+    bind(Scope::Main, Action::SaveSession, "save-session", "Save the session to the `--session` file", &["S"]),
+    bind(Scope::Main, Action::CommandLine, "command-line", "Enter a command, e.g. `:hide cpu*`", &[":"]),
+    bind(Scope::Main, Action::ShowInputs, "inputs", "Show the status of each input", &["i"]),
+
+    bind(Scope::Prompt, Action::Submit, "submit", "Submit", &["ENTER"]),
+    bind(Scope::Prompt, Action::Cancel, "cancel", "Cancel", &["ESC"]),
+    bind(Scope::Prompt, Action::Complete, "complete", "Complete the command or series label", &["TAB"]),
+
+    bind(Scope::Distribution, Action::CloseDistribution, "close", "Close the distribution view", &["q", "ESC"]),
+    bind(Scope::Distribution, Action::FewerBins, "fewer-bins", "Decrease bins", &["["]),
+    bind(Scope::Distribution, Action::MoreBins, "more-bins", "Increase bins", &["]"]),
+
+    bind(Scope::Warnings, Action::FocusWarnings, "focus", "Focus/defocus warnings", &["w"]),
+    bind(Scope::Warnings, Action::ScrollDown, "scroll-down", "Scroll down", &["j"]),
+    bind(Scope::Warnings, Action::ScrollUp, "scroll-up", "Scroll up", &["k"]),
+    bind(Scope::Warnings, Action::ScrollOldest, "scroll-oldest", "Scroll to the oldest warning", &["g"]),
+    bind(Scope::Warnings, Action::ScrollLatest, "scroll-latest", "Scroll to the latest warning", &["G"]),
+    bind(Scope::Warnings, Action::ZoomWarnings, "zoom", "Zoom warnings", &["z"]),
+    bind(Scope::Warnings, Action::FreezeWarnings, "freeze", "Freeze warnings", &["SPACE"]),
+
+    bind(Scope::Legend, Action::FocusLegend, "focus", "Focus/defocus legend", &["g"]),
+    bind(Scope::Legend, Action::MoveLegendLeft, "move-left", "Move window leftwards", &["H"]),
+    bind(Scope::Legend, Action::MoveLegendRight, "move-right", "Move window rightwards", &["L"]),
+    bind(Scope::Legend, Action::MoveLegendUp, "move-up", "Move window upwards", &["K"]),
+    bind(Scope::Legend, Action::MoveLegendDown, "move-down", "Move window downwards", &["J"]),
+    bind(Scope::Legend, Action::PreviousSeries, "previous", "Focus on the previous series", &["k"]),
+    bind(Scope::Legend, Action::NextSeries, "next", "Focus on the next series", &["j"]),
+    bind(Scope::Legend, Action::ToggleVisibility, "toggle-visibility", "Toggle series visibility", &["SPACE"]),
+    bind(Scope::Legend, Action::CycleStyle, "cycle-style", "Cycle series style (line, points, step, bars, stacked area)", &["s"]),
+    bind(Scope::Legend, Action::ToggleXAxis, "x-axis", "Plot other series against this series (X-Y mode)", &["x"]),
+    bind(Scope::Legend, Action::ShowDistribution, "distribution", "View the distribution of the series in the time window", &["d"]),
+    bind(Scope::Legend, Action::ChangeColor, "change-color", "Change series color with the next key", &["c"]),
+    bind(Scope::Legend, Action::Search, "search", "Filter series by a fuzzy match, or a regex starting with `~`", &["/"]),
+    bind(Scope::Legend, Action::ShowMatching, "show-matching", "Show all series matching the filter", &["a"]),
+    bind(Scope::Legend, Action::HideMatching, "hide-matching", "Hide all series matching the filter", &["n"]),
+    bind(Scope::Legend, Action::Solo, "solo", "Show only the series matching the filter, or the focused series", &["o"]),
+    bind(Scope::Legend, Action::CycleSort, "cycle-sort", "Cycle legend order (name, last value, max in window)", &["O"]),
+    bind(Scope::Legend, Action::ToggleCompact, "compact", "Toggle compact legend without values", &["C"]),
+    bind(Scope::Legend, Action::ToggleEmpty, "show-empty", "Toggle listing series without points in the time window", &["e"]),
+    bind(Scope::Legend, Action::ToggleGroup, "toggle-group", "Collapse/expand the focused group (`--group-separator`)", &["ENTER"]),
+    bind(Scope::Legend, Action::CycleAggregate, "aggregate", "Plot a collapsed group as the sum or average of its series", &["A"]),
+
+    bind(Scope::LegendColor, Action::MoreRed, "more-red", "Make series color more red", &["r"]),
+    bind(Scope::LegendColor, Action::LessRed, "less-red", "Make series color less red", &["R"]),
+    bind(Scope::LegendColor, Action::MoreGreen, "more-green", "Make series color more green", &["g"]),
+    bind(Scope::LegendColor, Action::LessGreen, "less-green", "Make series color less green", &["G"]),
+    bind(Scope::LegendColor, Action::MoreBlue, "more-blue", "Make series color more blue", &["b"]),
+    bind(Scope::LegendColor, Action::LessBlue, "less-blue", "Make series color less blue", &["B"]),
+    bind(Scope::LegendColor, Action::RandomColor, "random", "Change series color to another random color", &["c"]),
+
+    bind(Scope::Inputs, Action::CloseInputs, "close", "Close the input status panel", &["q", "ESC", "i"]),
+    bind(Scope::Inputs, Action::PreviousInput, "previous", "Select the previous input", &["k", "UP"]),
+    bind(Scope::Inputs, Action::NextInput, "next", "Select the next input", &["j", "DOWN"]),
+    bind(Scope::Inputs, Action::TogglePause, "toggle-pause", "Pause/resume the selected input, dropping its data while paused", &["p"]),
+    bind(Scope::Inputs, Action::StopInput, "stop", "Stop the selected input", &["x"]),
+    bind(Scope::Inputs, Action::AddInput, "add", "Open a file or FIFO, e.g. `--json PATH`", &["a"]),
+    bind(Scope::Inputs, Action::RunCommand, "run-command", "Read JSON Lines from a shell command", &["!"]),
+];
+
+/// Key bindings from the `[keys]` section of the config file,
+/// keyed by scope name and then action name.
+pub type KeyConfig = BTreeMap<String, BTreeMap<String, KeyList>>;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// The active mapping between keys and actions.
+pub struct Keymap {
+    actions: HashMap<(Scope, KeyCode), Action>,
+    keys:    HashMap<Action, Vec<KeyCode>>,
+}
+
+impl Keymap {
+    /// Creates a keymap from the default bindings, overridden by the bindings in `config`.
+    pub fn new(config: &KeyConfig) -> Result<Self> {
+        let mut keys: HashMap<Action, Vec<KeyCode>> = BINDINGS
+            .iter()
+            .map(|binding| {
+                let keys = binding
+                    .default_keys
+                    .iter()
+                    .map(|key| parse_key(key).expect("valid default key"));
+                (binding.action, keys.collect())
+            })
+            .collect();
+
+        for (scope_name, actions) in config {
+            let scope = Scope::ALL
+                .iter()
+                .copied()
+                .find(|scope| scope.name() == scope_name)
+                .with_context(|| format!("unknown key binding scope {scope_name:?}"))?;
+            for (action_name, key_list) in actions {
+                let binding = BINDINGS
+                    .iter()
+                    .find(|binding| binding.scope == scope && binding.name == action_name)
+                    .with_context(|| {
+                        format!("unknown action {action_name:?} in [keys.{scope_name}]")
+                    })?;
+                let key_names = match key_list {
+                    KeyList::One(key) => std::slice::from_ref(key),
+                    KeyList::Many(keys) => keys.as_slice(),
+                };
+                let codes = key_names
+                    .iter()
+                    .map(|key| parse_key(key))
+                    .collect::<Result<_>>()
+                    .with_context(|| format!("keys.{scope_name}.{action_name}"))?;
+                keys.insert(binding.action, codes);
+            }
+        }
+
+        let mut actions = HashMap::new();
+        for binding in BINDINGS {
+            for &code in &keys[&binding.action] {
+                if let Some(other) = actions.insert((binding.scope, code), binding.action) {
+                    let other = BINDINGS
+                        .iter()
+                        .find(|binding| binding.action == other)
+                        .expect("action has binding");
+                    anyhow::bail!(
+                        "key {} is bound to both {:?} and {:?} in [keys.{}]",
+                        KeyDisplay(code),
+                        other.name,
+                        binding.name,
+                        binding.scope.name(),
+                    );
+                }
+            }
+        }
+
+        Ok(Self { actions, keys })
+    }
+
+    /// Returns the action bound to the key pressed in `event` within `scope`.
+    pub fn action(&self, scope: Scope, event: &Event) -> Option<Action> {
+        let Event::Key(KeyEvent { code, .. }) = event else { return None };
+        self.actions.get(&(scope, *code)).copied()
+    }
+
+    /// Returns the keys bound to `action` in a human-readable form.
+    pub fn keys_display(&self, action: Action) -> String {
+        let keys = self.keys.get(&action).map_or(&[][..], Vec::as_slice);
+        keys.iter().map(|&code| KeyDisplay(code).to_string()).collect::<Vec<_>>().join(", ")
+    }
+
+    /// Lists the bindings of each scope as `(title, [(keys, description)])` for the help screen.
+    pub fn help_info(&self) -> Vec<(String, Vec<(String, &'static str)>)> {
+        Scope::ALL
+            .iter()
+            .map(|&scope| {
+                let entries = BINDINGS
+                    .iter()
+                    .filter(|binding| binding.scope == scope)
+                    .map(|binding| (self.keys_display(binding.action), binding.description))
+                    .collect();
+                (scope.title(self), entries)
+            })
+            .collect()
+    }
+}
+
+const NAMED_KEYS: &[(&str, KeyCode)] = &[
+    ("SPACE", KeyCode::Char(' ')),
+    ("ENTER", KeyCode::Enter),
+    ("ESC", KeyCode::Esc),
+    ("TAB", KeyCode::Tab),
+    ("BACKSPACE", KeyCode::Backspace),
+    ("DELETE", KeyCode::Delete),
+    ("UP", KeyCode::Up),
+    ("DOWN", KeyCode::Down),
+    ("LEFT", KeyCode::Left),
+    ("RIGHT", KeyCode::Right),
+    ("HOME", KeyCode::Home),
+    ("END", KeyCode::End),
+    ("PAGEUP", KeyCode::PageUp),
+    ("PAGEDOWN", KeyCode::PageDown),
+];
+
+/// Parses a single character or one of the names in `NAMED_KEYS`.
+fn parse_key(key: &str) -> Result<KeyCode> {
+    if let Some(&(_, code)) = NAMED_KEYS.iter().find(|(name, _)| name.eq_ignore_ascii_case(key)) {
+        return Ok(code);
+    }
+
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(char), None) => Ok(KeyCode::Char(char)),
+        _ => anyhow::bail!(
+            "invalid key {key:?}, expected a single character or one of {}",
+            NAMED_KEYS.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
+struct KeyDisplay(KeyCode);
+
+impl fmt::Display for KeyDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((name, _)) = NAMED_KEYS.iter().find(|(_, code)| *code == self.0) {
+            return f.write_str(name);
+        }
+        match self.0 {
+            KeyCode::Char(char) => write!(f, "{char}"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
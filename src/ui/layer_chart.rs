@@ -1,23 +1,67 @@
-use std::ops;
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime};
+use std::{iter, mem, ops};
 
 use anyhow::Result;
 use chrono::DateTime;
-use crossterm::event::{self, Event, KeyEvent};
+use crossterm::event::Event;
+use plotters::chart::{ChartContext, LabelAreaPosition};
 use plotters::coord;
-use plotters::prelude::{ChartBuilder, DrawingArea};
+use plotters::coord::cartesian::Cartesian2d;
+use plotters::coord::types::RangedCoordf64;
+use plotters::drawing::DrawingAreaErrorKind;
+use plotters::element::{Cross, DashedPathElement, PathElement, Polygon, Text};
+use plotters::prelude::{
+    ChartBuilder, DrawingArea, DrawingBackend, IntoDrawingArea as _, SVGBackend,
+};
 use plotters::series::LineSeries;
-use plotters::style::{IntoTextStyle, RGBColor, WHITE};
+use plotters::style::{IntoFont as _, IntoTextStyle, RGBColor};
 use plotters_ratatui_backend::{AreaResult, Draw, PlottersWidget, RatatuiBackend, CHAR_PIXEL_SIZE};
 use ratatui::style::{Style, Stylize as _};
 use ratatui::{layout, widgets};
 
-use super::data::{Cache, Freezable};
+use super::data::{Axis, Cache, DrawStyle, Freezable};
+use super::group::{aggregate_targets, GroupPath};
+use super::keymap::{Action, Scope};
 use super::layer_help::LayerHelp;
+use super::layer_inputs::LayerInputs;
+use super::layer_prompt::{LayerPrompt, PromptAction};
+use super::session::ChartState;
+use super::theme::{self, Theme};
 use super::{Context, HandleInput, Layer, LayerCommand, LayerTrait, Options};
+use crate::util::disp_float;
+
+mod heatmap;
+use heatmap::HeatmapWidget;
+mod xy;
+use xy::XyDrawImpl;
+
+#[derive(Debug, Clone, Copy)]
+pub enum MaxGap {
+    Seconds(f64),
+    /// A multiple of the median interval between consecutive samples.
+    MedianMultiple(f64),
+}
+
+impl MaxGap {
+    fn resolve(self, points: &[(f64, f64)]) -> Option<f64> {
+        match self {
+            Self::Seconds(secs) => Some(secs),
+            Self::MedianMultiple(multiple) => {
+                let mut intervals: Vec<f64> =
+                    points.windows(2).map(|pair| pair[1].0 - pair[0].0).collect();
+                intervals.sort_by(f64::total_cmp);
+                intervals.get(intervals.len() / 2).map(|median| median * multiple)
+            }
+        }
+    }
+}
 
 pub struct LayerChart {
-    freeze: Option<Box<Freeze>>,
+    freeze:  Option<Box<Freeze>>,
+    heatmap: bool,
+    y_log:   bool,
 
     x_start: Duration,
     x_end:   Duration,
@@ -25,10 +69,54 @@ pub struct LayerChart {
 
 impl LayerChart {
     pub fn new(options: &Options) -> Self {
-        Self { freeze: None, x_start: options.data_backlog_duration, x_end: Duration::ZERO }
+        Self {
+            freeze:  None,
+            heatmap: false,
+            y_log:   false,
+            x_start: options.data_backlog_duration,
+            x_end:   Duration::ZERO,
+        }
+    }
+
+    pub fn session_state(&self) -> ChartState {
+        ChartState {
+            x_start: self.x_start.as_secs_f64(),
+            x_end:   self.x_end.as_secs_f64(),
+            heatmap: self.heatmap,
+            y_log:   self.y_log,
+        }
+    }
+
+    pub fn restore_session(&mut self, state: &ChartState) {
+        self.x_start = Duration::try_from_secs_f64(state.x_start).unwrap_or(self.x_start);
+        self.x_end = Duration::try_from_secs_f64(state.x_end).unwrap_or(self.x_end);
+        self.heatmap = state.heatmap;
+        self.y_log = state.y_log;
+    }
+
+    /// Applies a command from the command line, returning the path to export the chart to if any.
+    fn run_command(&mut self, command: ChartCommand) -> Option<PathBuf> {
+        match command {
+            ChartCommand::Window(duration) => {
+                self.x_start = duration;
+                self.x_end = Duration::ZERO;
+            }
+            ChartCommand::ToggleYLog => self.y_log = !self.y_log,
+            ChartCommand::Export(path) => return Some(path),
+        }
+        None
     }
 }
 
+/// Commands from the command line applied to the chart on the next render.
+pub enum ChartCommand {
+    /// Show the last duration of data.
+    Window(Duration),
+    ToggleYLog,
+    /// Export the time chart as an SVG file.
+    Export(PathBuf),
+}
+
 struct Freeze {
     frozen: SystemTime,
     data:   Freezable,
@@ -42,8 +130,14 @@ struct RenderTimeRange {
 }
 
 struct DrawImpl<'t> {
-    time:    RenderTimeRange,
-    targets: &'t [DrawTarget],
+    time:       RenderTimeRange,
+    targets:    &'t [DrawTarget],
+    markers:    &'t [DrawMarker],
+    max_gap:    Option<MaxGap>,
+    shade_gaps: bool,
+    /// Whether the values of `targets` are in log10 scale.
+    y_log:      bool,
+    theme:      &'t Theme,
 }
 
 impl RenderTimeRange {
@@ -57,10 +151,35 @@ impl RenderTimeRange {
 }
 
 pub(super) struct DrawTarget {
-    pub(super) points:  Vec<(f64, f64)>,
-    pub(super) visible: bool,
-    pub(super) color:   [u8; 3],
-    pub(super) label:   String,
+    pub(super) points:     Vec<(f64, f64)>,
+    pub(super) visible:    bool,
+    pub(super) color:      [u8; 3],
+    pub(super) style:      DrawStyle,
+    pub(super) axis:       Axis,
+    pub(super) thresholds: Vec<f64>,
+    pub(super) label:      String,
+    /// The group aggregated by this target, or `None` for a single series.
+    pub(super) group:      Option<GroupPath>,
+}
+
+struct DrawMarker {
+    x:    f64,
+    text: String,
+}
+
+fn data_to_markers(data: &Freezable, time: RenderTimeRange) -> Vec<DrawMarker> {
+    data.annotations
+        .iter()
+        .filter(|annotation| time.abs_range().contains(&annotation.time))
+        .map(|annotation| {
+            let x = time
+                .now
+                .duration_since(annotation.time)
+                .expect("time should be in the past")
+                .as_secs_f64();
+            DrawMarker { x: -x, text: annotation.text.clone() }
+        })
+        .collect()
 }
 
 fn data_to_targets(cache: &Cache, data: &Freezable, time: RenderTimeRange) -> Vec<DrawTarget> {
@@ -85,58 +204,252 @@ fn data_to_targets(cache: &Cache, data: &Freezable, time: RenderTimeRange) -> Ve
                     (-x, y)
                 })
                 .collect();
-            DrawTarget { points, visible: disp.visible, color: disp.color, label: label.clone() }
+            DrawTarget {
+                points,
+                visible: disp.visible,
+                color: disp.color,
+                style: disp.style,
+                axis: disp.axis,
+                thresholds: disp.thresholds.clone(),
+                label: label.clone(),
+                group: None,
+            }
         })
         .collect()
 }
 
+type Chart<'a, DB> = ChartContext<'a, DB, Cartesian2d<RangedCoordf64, RangedCoordf64>>;
+type DrawResult<DB> = Result<(), DrawingAreaErrorKind<<DB as DrawingBackend>::ErrorType>>;
+
 impl Draw for DrawImpl<'_> {
     fn draw(&self, area: DrawingArea<RatatuiBackend, coord::Shift>) -> AreaResult {
-        let global_y_extrema = self
+        self.draw_on(&area, CHAR_PIXEL_SIZE)
+    }
+}
+
+impl DrawImpl<'_> {
+    /// Draws the chart on any backend, with text and margins scaled to `char_size` pixels.
+    fn draw_on<DB: DrawingBackend>(
+        &self,
+        area: &DrawingArea<DB, coord::Shift>,
+        char_size: u32,
+    ) -> DrawResult<DB> {
+        let max_gaps: Vec<_> = self
             .targets
             .iter()
-            .flat_map(|target| &target.points)
-            .map(|&(_, y)| y)
-            .fold(None::<(f64, f64)>, |extrema, y| {
-                let (min, max) = extrema.unwrap_or((y, y));
-                Some((min.min(y), max.max(y)))
-            })
-            .unwrap_or((0.0, 1.0));
+            .map(|target| self.max_gap.and_then(|max_gap| max_gap.resolve(&target.points)))
+            .collect();
+        let stacked = stack_areas(self.targets, &max_gaps);
+
+        let mapping = match (
+            y_extrema(self.targets, &stacked, Axis::Left),
+            y_extrema(self.targets, &stacked, Axis::Right),
+        ) {
+            (Some(left), right) => AxisMapping { left, right },
+            // plot right axis series on the left axis if there are no left axis series
+            (None, right) => AxisMapping { left: right.unwrap_or((0.0, 1.0)), right: None },
+        };
 
         let x_range = self.time.neg_secs_range();
-        let y_range = global_y_extrema.0..global_y_extrema.1;
+        let y_range = mapping.left.0..mapping.left.1;
 
-        let mut chart = ChartBuilder::on(&area)
-            .margin_left(24)
-            .margin_bottom(12)
-            .set_left_and_bottom_label_area_size(1)
-            .build_cartesian_2d(x_range, y_range)?;
+        let mut builder = ChartBuilder::on(area);
+        builder
+            .margin_left(char_size * 6)
+            .margin_bottom(char_size * 3)
+            .set_left_and_bottom_label_area_size(1);
+        if mapping.right.is_some() {
+            builder.margin_right(char_size * 6).set_label_area_size(LabelAreaPosition::Right, 1);
+        }
+        let mut chart = builder.build_cartesian_2d(x_range.clone(), y_range.clone())?;
 
-        for &DrawTarget { ref points, visible, color: [color_r, color_g, color_b], .. } in
-            self.targets
-        {
-            if visible {
-                chart.draw_series(LineSeries::new(
-                    points.iter().copied(),
-                    RGBColor(color_r, color_g, color_b),
+        if self.shade_gaps {
+            for (target, &max_gap) in iter::zip(self.targets, &max_gaps) {
+                let Some(max_gap) = max_gap.filter(|_| target.visible) else { continue };
+                chart.draw_series(gap_shades(
+                    &target.points,
+                    max_gap,
+                    y_range.clone(),
+                    theme::plot(self.theme.gap),
                 ))?;
             }
         }
 
+        for ((target, stacked), &max_gap) in iter::zip(iter::zip(self.targets, &stacked), &max_gaps)
+        {
+            if target.visible {
+                draw_target(&mut chart, target, stacked.as_ref(), max_gap, &mapping)?;
+            }
+        }
+
+        // stagger marker labels vertically so that adjacent markers do not overlap completely
+        let label_step = (y_range.end - y_range.start) / 16.;
+        for (i, marker) in self.markers.iter().enumerate() {
+            chart.draw_series(iter::once(PathElement::new(
+                [(marker.x, y_range.start), (marker.x, y_range.end)],
+                theme::plot(self.theme.marker),
+            )))?;
+            chart.draw_series(iter::once(Text::new(
+                marker.text.clone(),
+                (marker.x, y_range.end - label_step * (i % 4) as f64),
+                ("", char_size).into_font().color(&theme::plot(self.theme.marker)),
+            )))?;
+        }
+
         chart
             .configure_mesh()
             .disable_mesh()
-            .axis_style(WHITE)
-            .label_style(("", CHAR_PIXEL_SIZE).with_color(WHITE))
+            .axis_style(theme::plot(self.theme.axis))
+            .label_style(("", char_size).with_color(theme::plot(self.theme.axis)))
             .x_label_formatter(&|&value| {
                 DateTime::<chrono::Local>::from(self.time.secs_to_abs(value))
                     .format("%H:%M:%S")
                     .to_string()
             })
+            .y_label_formatter(&|&value| self.format_y(value))
             .draw()?;
 
+        if let Some((right_min, right_max)) = mapping.right {
+            let mut chart = chart.set_secondary_coord(x_range, right_min..right_max);
+            chart
+                .configure_secondary_axes()
+                .axis_style(theme::plot(self.theme.axis))
+                .label_style(("", char_size).with_color(theme::plot(self.theme.axis)))
+                .y_label_formatter(&|&value| self.format_y(value))
+                .draw()?;
+        }
+
         Ok(())
     }
+
+    fn format_y(&self, value: f64) -> String {
+        if self.y_log {
+            disp_float(10f64.powf(value), 3)
+        } else {
+            disp_float(value, 3)
+        }
+    }
+}
+
+fn draw_target<DB: DrawingBackend>(
+    chart: &mut Chart<DB>,
+    target: &DrawTarget,
+    stacked: Option<&StackedArea>,
+    max_gap: Option<f64>,
+    mapping: &AxisMapping,
+) -> DrawResult<DB> {
+    let [color_r, color_g, color_b] = target.color;
+    let color = RGBColor(color_r, color_g, color_b);
+    let points = &mapping.project_points(target.axis, &target.points);
+
+    match target.style {
+        DrawStyle::Line => {
+            for segment in split_gaps(points, max_gap) {
+                if let &[point] = segment {
+                    chart.draw_series(iter::once(Cross::new(point, 1, color)))?;
+                } else {
+                    chart.draw_series(LineSeries::new(segment.iter().copied(), color))?;
+                }
+            }
+        }
+        DrawStyle::Points => {
+            chart.draw_series(points.iter().map(|&point| Cross::new(point, 1, color)))?;
+        }
+        DrawStyle::Step => {
+            for segment in split_gaps(points, max_gap) {
+                if let &[point] = segment {
+                    chart.draw_series(iter::once(Cross::new(point, 1, color)))?;
+                } else {
+                    chart.draw_series(LineSeries::new(step_points(segment), color))?;
+                }
+            }
+        }
+        DrawStyle::Bars => {
+            let (axis_min, axis_max) = mapping.range(target.axis);
+            let bar_base = mapping.project(target.axis, 0f64.clamp(axis_min, axis_max));
+            chart.draw_series(points.iter().enumerate().filter_map(|(i, &(x, y))| {
+                let width = match (i.checked_sub(1).map(|i| points[i]), points.get(i + 1)) {
+                    (Some((prev_x, _)), _) => x - prev_x,
+                    (None, Some(&(next_x, _))) => next_x - x,
+                    (None, None) => return None,
+                };
+                // the first bar after a gap would otherwise stretch across the gap
+                let width = max_gap.map_or(width, |max_gap| width.min(max_gap));
+                Some(Polygon::new(
+                    [(x - width, bar_base), (x - width, y), (x, y), (x, bar_base)],
+                    color,
+                ))
+            }))?;
+        }
+        DrawStyle::StackedArea => {
+            let stacked = stacked.expect("visible stacked series are stacked");
+            // the areas between the segments of the series are left empty
+            for segment in split_gaps(&target.points, max_gap) {
+                let (start, end) = (segment[0].0, segment[segment.len() - 1].0);
+                let in_segment = |&&(x, _): &&(f64, f64)| start <= x && x <= end;
+                let outline: Vec<_> = stacked
+                    .top
+                    .iter()
+                    .filter(in_segment)
+                    .chain(stacked.bottom.iter().filter(in_segment).rev())
+                    .copied()
+                    .collect();
+                let outline = mapping.project_points(target.axis, &outline);
+                if segment.len() == 1 {
+                    chart.draw_series(iter::once(Cross::new(outline[0], 1, color)))?;
+                } else {
+                    chart.draw_series(iter::once(Polygon::new(outline, color)))?;
+                }
+            }
+        }
+    }
+
+    let x_range = chart.x_range();
+    for &threshold in &target.thresholds {
+        let y = mapping.project(target.axis, threshold);
+        chart.draw_series(iter::once(DashedPathElement::new(
+            [(x_range.start, y), (x_range.end, y)],
+            2,
+            4,
+            color,
+        )))?;
+    }
+
+    Ok(())
+}
+
+/// Maps values on the right axis to the coordinates of the left axis,
+/// so that series on both axes can be drawn in the same coordinate system.
+struct AxisMapping {
+    left:  (f64, f64),
+    right: Option<(f64, f64)>,
+}
+
+impl AxisMapping {
+    fn range(&self, axis: Axis) -> (f64, f64) {
+        match (axis, self.right) {
+            (Axis::Right, Some(right)) => right,
+            _ => self.left,
+        }
+    }
+
+    fn project(&self, axis: Axis, y: f64) -> f64 {
+        match (axis, self.right) {
+            (Axis::Right, Some((min, max))) => {
+                self.left.0 + (y - min) / (max - min) * (self.left.1 - self.left.0)
+            }
+            _ => y,
+        }
+    }
+
+    fn project_points<'t>(&self, axis: Axis, points: &'t [(f64, f64)]) -> Cow<'t, [(f64, f64)]> {
+        match (axis, self.right) {
+            (Axis::Right, Some(_)) => {
+                points.iter().map(|&(x, y)| (x, self.project(axis, y))).collect()
+            }
+            _ => Cow::Borrowed(points),
+        }
+    }
 }
 
 impl LayerTrait for LayerChart {
@@ -144,6 +457,11 @@ impl LayerTrait for LayerChart {
     fn render(&mut self, context: &mut Context, frame: &mut ratatui::Frame) {
         const SCROLL_DENOMINATOR: usize = 1000;
 
+        let exports: Vec<_> = mem::take(&mut context.chart_commands)
+            .into_iter()
+            .filter_map(|command| self.run_command(command))
+            .collect();
+
         let (now, data) = if let Some(freeze) = &self.freeze {
             (freeze.frozen, &freeze.data)
         } else {
@@ -152,20 +470,61 @@ impl LayerTrait for LayerChart {
         };
 
         let time = RenderTimeRange { now, since_start: self.x_start, since_end: self.x_end };
-        let targets = &*context.current_targets.insert(data_to_targets(&context.cache, data, time));
-
-        let chart = PlottersWidget {
-            draw:          DrawImpl { time, targets },
-            error_handler: |err| {
-                context.warning_sender.clone().send(format!("Plotting error: {err:?}"));
-            },
+        let mut targets = data_to_targets(&context.cache, data, time);
+        aggregate_targets(&mut targets, &context.cache, &context.grouping, &context.aggregates);
+        let targets = &*context.current_targets.insert(targets);
+        let markers = data_to_markers(data, time);
+        let log_targets = self.y_log.then(|| log_targets(targets));
+        let draw = DrawImpl {
+            time,
+            targets: log_targets.as_deref().unwrap_or(targets),
+            markers: &markers,
+            max_gap: context.options.max_gap,
+            shade_gaps: context.options.shade_gaps,
+            y_log: self.y_log,
+            theme: &context.theme,
         };
+
+        for path in exports {
+            match export_svg(&draw, &path) {
+                Ok(()) => context.warning_sender.send(format!("Exported to {}", path.display())),
+                Err(err) => context.warning_sender.send(format!("Error: {err:?}")),
+            }
+        }
+
         let rect = frame.area();
-        frame.render_widget(chart, rect.inner(layout::Margin { vertical: 1, horizontal: 0 }));
+        let chart_rect = rect.inner(layout::Margin { vertical: 1, horizontal: 0 });
+        let error_handler = |err| {
+            context.warning_sender.clone().send(format!("Plotting error: {err:?}"));
+        };
+        if self.heatmap {
+            frame.render_widget(
+                HeatmapWidget { targets, x_range: time.neg_secs_range() },
+                chart_rect,
+            );
+        } else if let Some(x_label) = &context.x_series {
+            let neg_secs_range = time.neg_secs_range();
+            let chart = PlottersWidget {
+                draw: XyDrawImpl {
+                    targets,
+                    x_label,
+                    time: (neg_secs_range.start, neg_secs_range.end),
+                    theme: &context.theme,
+                },
+                error_handler,
+            };
+            frame.render_widget(chart, chart_rect);
+            frame.render_widget(
+                widgets::Paragraph::new(format!("X: {x_label}")).style(Style::default().bold()),
+                rect,
+            );
+        } else {
+            frame.render_widget(PlottersWidget { draw, error_handler }, chart_rect);
+        }
 
         let x_start_display = self.x_start.min(context.options.data_backlog_duration);
         let x_midpt_display = ((x_start_display + self.x_end) / 2).as_secs_f64();
-        let x_interval_display = (x_start_display - self.x_end).as_secs_f64();
+        let x_interval_display = x_start_display.saturating_sub(self.x_end).as_secs_f64();
         let scroll_interval_ratio =
             x_interval_display / context.options.data_backlog_duration.as_secs_f64();
         let scroll_midpt_ratio = (context.options.data_backlog_duration.as_secs_f64()
@@ -184,7 +543,7 @@ impl LayerTrait for LayerChart {
 
         let mut begin_style = Style::default();
         if self.x_start > context.options.data_backlog_duration {
-            begin_style = begin_style.light_red();
+            begin_style = begin_style.fg(context.theme.alert);
         }
 
         frame.render_stateful_widget(
@@ -202,16 +561,47 @@ impl LayerTrait for LayerChart {
         layer_cmds: &mut Vec<LayerCommand>,
         _frame_size: layout::Rect,
     ) -> Result<HandleInput> {
-        Ok(match event {
-            Event::Key(KeyEvent { code: event::KeyCode::Char('q'), .. }) => {
+        let Some(action) = context.keymap.action(Scope::Main, event) else {
+            return Ok(HandleInput::Fallthru);
+        };
+
+        Ok(match action {
+            Action::Quit => {
                 context.cancel.cancel();
                 HandleInput::Consumed
             }
-            Event::Key(KeyEvent { code: event::KeyCode::Char('?'), .. }) => {
+            Action::ShowHelp => {
                 layer_cmds.push(LayerCommand::Insert(Layer::Help(LayerHelp), 1));
                 HandleInput::Consumed
             }
-            Event::Key(KeyEvent { code: event::KeyCode::Char(' '), .. }) => {
+            Action::ShowInputs => {
+                layer_cmds.push(LayerCommand::Push(Layer::Inputs(LayerInputs::default())));
+                HandleInput::Consumed
+            }
+            Action::SaveSession => {
+                if context.options.session.is_some() {
+                    context.save_session_requested = true;
+                } else {
+                    context.warning_sender.send("Pass `--session PATH` to save the session");
+                }
+                HandleInput::Consumed
+            }
+            Action::ToggleHeatmap => {
+                self.heatmap = !self.heatmap;
+                HandleInput::Consumed
+            }
+            Action::CommandLine => {
+                let prompt = LayerPrompt::new(":", PromptAction::Command);
+                layer_cmds.push(LayerCommand::Push(Layer::Prompt(prompt)));
+                HandleInput::Consumed
+            }
+            Action::AddMarker => {
+                let prompt =
+                    LayerPrompt::new("Marker note", PromptAction::Marker(SystemTime::now()));
+                layer_cmds.push(LayerCommand::Push(Layer::Prompt(prompt)));
+                HandleInput::Consumed
+            }
+            Action::Freeze => {
                 self.freeze = match self.freeze {
                     Some(_) => None,
                     None => Some(Box::new(Freeze {
@@ -221,29 +611,31 @@ impl LayerTrait for LayerChart {
                 };
                 HandleInput::Consumed
             }
-            Event::Key(KeyEvent {
-                code: event::KeyCode::Char(key @ ('-' | '=' | 'h' | 'l' | 'H' | 'L')),
-                ..
-            }) => {
+            Action::ZoomOut
+            | Action::ZoomIn
+            | Action::PanLeft
+            | Action::PanRight
+            | Action::PanLeftFar
+            | Action::PanRightFar => {
                 #[allow(clippy::type_complexity)]
                 let (itv_fn, midpt_fn): (
                     fn(Duration) -> Duration,
                     fn(Duration, Duration) -> Duration,
-                ) = match key {
-                    '-' => (|itv| itv * 5 / 4, |midpt, _| midpt),
-                    '=' => (|itv| itv * 4 / 5, |midpt, _| midpt),
-                    'h' => (|itv| itv, |midpt, itv| midpt + itv / 10),
-                    'l' => (|itv| itv, |midpt, itv| midpt.saturating_sub(itv / 10)),
-                    'H' => (|itv| itv, |midpt, itv| midpt + itv / 2),
-                    'L' => (|itv| itv, |midpt, itv| midpt.saturating_sub(itv / 2)),
+                ) = match action {
+                    Action::ZoomOut => (|itv| itv * 5 / 4, |midpt, _| midpt),
+                    Action::ZoomIn => (|itv| itv * 4 / 5, |midpt, _| midpt),
+                    Action::PanLeft => (|itv| itv, |midpt, itv| midpt + itv / 10),
+                    Action::PanRight => (|itv| itv, |midpt, itv| midpt.saturating_sub(itv / 10)),
+                    Action::PanLeftFar => (|itv| itv, |midpt, itv| midpt + itv / 2),
+                    Action::PanRightFar => (|itv| itv, |midpt, itv| midpt.saturating_sub(itv / 2)),
                     _ => unreachable!(),
                 };
 
                 let midpt = (self.x_start + self.x_end) / 2;
 
-                let left_semiitv = itv_fn(self.x_start - midpt);
-                let right_semiitv = itv_fn(midpt - self.x_end);
-                let new_midpt = midpt_fn(midpt, self.x_start - self.x_end);
+                let left_semiitv = itv_fn(self.x_start.saturating_sub(midpt));
+                let right_semiitv = itv_fn(midpt.saturating_sub(self.x_end));
+                let new_midpt = midpt_fn(midpt, self.x_start.saturating_sub(self.x_end));
 
                 let start =
                     (new_midpt + left_semiitv).min(context.options.data_backlog_duration * 2);
@@ -253,7 +645,7 @@ impl LayerTrait for LayerChart {
                 (self.x_start, self.x_end) = (start, end);
                 HandleInput::Consumed
             }
-            Event::Key(KeyEvent { code: event::KeyCode::Char('r'), .. }) => {
+            Action::ResetView => {
                 self.x_start = context.options.data_backlog_duration;
                 self.x_end = Duration::ZERO;
                 HandleInput::Consumed
@@ -263,4 +655,187 @@ impl LayerTrait for LayerChart {
     }
 }
 
+/// Converts the values and thresholds of targets to log10 scale, dropping non-positive values.
+fn log_targets(targets: &[DrawTarget]) -> Vec<DrawTarget> {
+    let log = |values: &mut dyn Iterator<Item = f64>| {
+        values.filter(|&value| value > 0.).map(f64::log10).collect::<Vec<_>>()
+    };
+    targets
+        .iter()
+        .map(|target| DrawTarget {
+            points:     target
+                .points
+                .iter()
+                .filter(|&&(_, y)| y > 0.)
+                .map(|&(x, y)| (x, y.log10()))
+                .collect(),
+            visible:    target.visible,
+            color:      target.color,
+            style:      target.style,
+            axis:       target.axis,
+            thresholds: log(&mut target.thresholds.iter().copied()),
+            label:      target.label.clone(),
+            group:      target.group.clone(),
+        })
+        .collect()
+}
+
+fn export_svg(draw: &DrawImpl, path: &Path) -> Result<()> {
+    const SIZE: (u32, u32) = (1200, 600);
+    const CHAR_SIZE: u32 = 12;
+
+    let area = SVGBackend::new(path, SIZE).into_drawing_area();
+    let map_err = |err| anyhow::anyhow!("export {}: {err}", path.display());
+    area.fill(&theme::plot(draw.theme.background)).map_err(map_err)?;
+    draw.draw_on(&area, CHAR_SIZE).map_err(map_err)?;
+    area.present().map_err(map_err)
+}
+
+/// Computes the range of values to display for series on `axis`,
+/// or `None` if there are no series on `axis`.
+fn y_extrema(
+    targets: &[DrawTarget],
+    stacked: &[Option<StackedArea>],
+    axis: Axis,
+) -> Option<(f64, f64)> {
+    let (min, max) = iter::zip(targets, stacked)
+        .filter(|(target, _)| target.axis == axis)
+        .flat_map(|(target, stacked)| {
+            let points = match stacked {
+                Some(stacked) => &stacked.top,
+                None => &target.points,
+            };
+            let baseline = (target.visible
+                && matches!(target.style, DrawStyle::Bars | DrawStyle::StackedArea))
+            .then_some(0.);
+            let thresholds = target.thresholds.iter().copied().filter(|_| target.visible);
+            points.iter().map(|&(_, y)| y).chain(baseline).chain(thresholds)
+        })
+        .fold(None::<(f64, f64)>, |extrema, y| {
+            let (min, max) = extrema.unwrap_or((y, y));
+            Some((min.min(y), max.max(y)))
+        })?;
+
+    Some(if min < max { (min, max) } else { (min - 0.5, max + 0.5) })
+}
+
+fn gap_shades(
+    points: &[(f64, f64)],
+    max_gap: f64,
+    y_range: ops::Range<f64>,
+    color: RGBColor,
+) -> impl Iterator<Item = Polygon<(f64, f64)>> + '_ {
+    points.windows(2).filter(move |pair| pair[1].0 - pair[0].0 > max_gap).map(move |pair| {
+        let (start, end) = (pair[0].0, pair[1].0);
+        Polygon::new(
+            [
+                (start, y_range.start),
+                (start, y_range.end),
+                (end, y_range.end),
+                (end, y_range.start),
+            ],
+            color,
+        )
+    })
+}
+
+struct StackedArea {
+    top:    Vec<(f64, f64)>,
+    bottom: Vec<(f64, f64)>,
+}
+
+/// Computes the boundaries of visible stacked area series,
+/// where each series is stacked on top of the previous ones on the same axis in label order.
+///
+/// A series adds nothing to the stack during its gaps longer than its entry in `max_gaps`.
+fn stack_areas(targets: &[DrawTarget], max_gaps: &[Option<f64>]) -> Vec<Option<StackedArea>> {
+    // series on different axes are stacked separately
+    let mut below_by_axis: [Vec<(f64, f64)>; 2] = Default::default();
+
+    iter::zip(targets, max_gaps)
+        .map(|(target, &max_gap)| {
+            if !target.visible || target.style != DrawStyle::StackedArea {
+                return None;
+            }
+            let below = &mut below_by_axis[usize::from(target.axis == Axis::Right)];
+
+            let mut xs: Vec<f64> = below.iter().chain(&target.points).map(|&(x, _)| x).collect();
+            xs.sort_by(f64::total_cmp);
+            xs.dedup();
+
+            let bottom: Vec<_> = xs.iter().map(|&x| (x, hold_value(below, x, None))).collect();
+            let top: Vec<_> = bottom
+                .iter()
+                .map(|&(x, y)| (x, y + hold_value(&target.points, x, max_gap)))
+                .collect();
+            below.clone_from(&top);
+            Some(StackedArea { top, bottom })
+        })
+        .collect()
+}
+
+/// Returns the value of the last point at or before `x`,
+/// or zero if there is none or if `x` is in a gap longer than `max_gap`.
+fn hold_value(points: &[(f64, f64)], x: f64, max_gap: Option<f64>) -> f64 {
+    let index = points.partition_point(|&(point_x, _)| point_x <= x);
+    let Some(&(point_x, value)) = index.checked_sub(1).map(|index| &points[index]) else {
+        return 0.;
+    };
+    let in_gap = max_gap.is_some_and(|max_gap| {
+        points.get(index).is_some_and(|&(next_x, _)| next_x - point_x > max_gap)
+    });
+    if in_gap && x > point_x {
+        0.
+    } else {
+        value
+    }
+}
+
+/// Splits points into segments wherever consecutive points are further apart than `max_gap`.
+fn split_gaps(points: &[(f64, f64)], max_gap: Option<f64>) -> impl Iterator<Item = &[(f64, f64)]> {
+    points.chunk_by(move |prev, next| max_gap.is_none_or(|max_gap| next.0 - prev.0 <= max_gap))
+}
+
+/// Converts points into a step-after polyline,
+/// where each value is held until the next point.
+fn step_points(points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut output = Vec::with_capacity(points.len() * 2);
+    for (i, &(x, y)) in points.iter().enumerate() {
+        if let Some(&(_, prev_y)) = i.checked_sub(1).map(|i| &points[i]) {
+            output.push((x, prev_y));
+        }
+        output.push((x, y));
+    }
+    output
+}
+
 fn unlerp(a: f64, b: f64, v: f64) -> f64 { (v - a) / (b - a) }
+
+#[cfg(test)]
+mod tests {
+    use super::{split_gaps, MaxGap};
+
+    #[test]
+    fn split_at_gaps() {
+        let points = [(0., 1.), (1., 2.), (2., 3.), (10., 4.), (11., 5.), (20., 6.)];
+        let segments: Vec<_> = split_gaps(&points, Some(2.)).collect();
+        assert_eq!(segments, [&points[..3], &points[3..5], &points[5..]]);
+
+        // a gap equal to the maximum does not split
+        let segments: Vec<_> = split_gaps(&points, Some(8.)).collect();
+        assert_eq!(segments, [&points[..5], &points[5..]]);
+
+        assert_eq!(split_gaps(&points, None).collect::<Vec<_>>(), [&points[..]]);
+        assert_eq!(split_gaps(&[], Some(1.)).count(), 0);
+    }
+
+    #[test]
+    fn resolve_max_gap() {
+        let points = [(0., 0.), (1., 0.), (2., 0.), (10., 0.), (11., 0.)];
+        assert_eq!(MaxGap::Seconds(5.).resolve(&points), Some(5.));
+        assert_eq!(MaxGap::Seconds(5.).resolve(&[]), Some(5.));
+        // intervals are 1, 1, 8 and 1, so the median is 1
+        assert_eq!(MaxGap::MedianMultiple(3.).resolve(&points), Some(3.));
+        assert_eq!(MaxGap::MedianMultiple(3.).resolve(&points[..1]), None);
+    }
+}
@@ -0,0 +1,154 @@
+use std::ops;
+
+use ratatui::buffer::Buffer;
+use ratatui::layout;
+use ratatui::style::{self, Style, Stylize as _};
+use ratatui::widgets::Widget;
+
+use super::DrawTarget;
+use crate::util::disp_float;
+
+/// Color stops of the gradient, sampled from the viridis color map.
+const GRADIENT: &[[u8; 3]] =
+    &[[68, 1, 84], [59, 82, 139], [33, 145, 140], [94, 201, 98], [253, 231, 37]];
+
+/// Draws one row per visible series with time on the x axis,
+/// where the mean value of each time bucket is mapped to a color gradient.
+///
+/// Series beyond two per line are not drawn, but counted on the last line.
+pub(super) struct HeatmapWidget<'t> {
+    pub(super) targets: &'t [DrawTarget],
+    pub(super) x_range: ops::Range<f64>,
+}
+
+impl Widget for HeatmapWidget<'_> {
+    fn render(self, area: layout::Rect, buf: &mut Buffer) {
+        let targets: Vec<_> = self.targets.iter().filter(|target| target.visible).collect();
+        if targets.is_empty() || area.height < 2 {
+            return;
+        }
+
+        let Some((min, max)) = targets
+            .iter()
+            .flat_map(|target| &target.points)
+            .map(|&(_, y)| y)
+            .fold(None::<(f64, f64)>, |extrema, y| {
+                let (min, max) = extrema.unwrap_or((y, y));
+                Some((min.min(y), max.max(y)))
+            })
+        else {
+            return;
+        };
+
+        // reserve the last line for the color scale
+        let rows_area = layout::Rect { height: area.height - 1, ..area };
+        // use half blocks to fit two series per line if there is not enough space
+        let series_per_line = if targets.len() > usize::from(rows_area.height) { 2 } else { 1 };
+
+        let label_width = targets
+            .iter()
+            .map(|target| target.label.chars().count())
+            .max()
+            .unwrap_or(0)
+            .min(usize::from(area.width / 4)) as u16;
+        let cells_x = area.x + label_width + 1;
+        let cells_width = area.right().saturating_sub(cells_x);
+
+        // if the series still do not fit, the last line tells how many are not shown
+        let mut lines = targets.len().div_ceil(series_per_line);
+        if lines > usize::from(rows_area.height) {
+            lines = usize::from(rows_area.height) - 1;
+            let hidden = targets.len() - lines * series_per_line;
+            buf.set_stringn(
+                area.x,
+                rows_area.bottom() - 1,
+                format!("{hidden} more series hidden, hide some series to show them"),
+                usize::from(area.width),
+                Style::default().italic(),
+            );
+        }
+
+        for (line, chunk) in targets.chunks(series_per_line).take(lines).enumerate() {
+            let y = rows_area.y + line as u16;
+
+            let label = chunk.iter().map(|target| target.label.as_str()).collect::<Vec<_>>();
+            let [color_r, color_g, color_b] = chunk[0].color;
+            buf.set_stringn(
+                area.x,
+                y,
+                label.join(", "),
+                usize::from(label_width),
+                Style::default().fg(style::Color::Rgb(color_r, color_g, color_b)),
+            );
+
+            let buckets: Vec<_> = chunk
+                .iter()
+                .map(|target| bucket_means(&target.points, self.x_range.clone(), cells_width))
+                .collect();
+            for column in 0..cells_width {
+                let colors: Vec<_> = buckets
+                    .iter()
+                    .map(|means| means[usize::from(column)].map(|mean| gradient(min, max, mean)))
+                    .collect();
+                let Some(cell) = buf.cell_mut((cells_x + column, y)) else { continue };
+                match colors[..] {
+                    [Some(color)] => _ = cell.set_char(' ').set_bg(color),
+                    [upper, lower] => {
+                        cell.set_char('▀');
+                        if let Some(upper) = upper {
+                            cell.set_fg(upper);
+                        }
+                        if let Some(lower) = lower {
+                            cell.set_bg(lower);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let scale_y = area.bottom() - 1;
+        let min_label = disp_float(min, 3);
+        let max_label = disp_float(max, 3);
+        buf.set_string(area.x, scale_y, &min_label, Style::default().bold());
+        let scale_x = area.x + min_label.len() as u16 + 1;
+        let scale_width = area.right().saturating_sub(scale_x + max_label.len() as u16 + 1);
+        for column in 0..scale_width {
+            let value = min + (max - min) * f64::from(column) / f64::from(scale_width.max(2) - 1);
+            if let Some(cell) = buf.cell_mut((scale_x + column, scale_y)) {
+                cell.set_char(' ').set_bg(gradient(min, max, value));
+            }
+        }
+        buf.set_string(scale_x + scale_width + 1, scale_y, &max_label, Style::default().bold());
+    }
+}
+
+/// Computes the mean of points in each of `buckets` equal time buckets over `x_range`.
+#[allow(clippy::cast_sign_loss)]
+fn bucket_means(points: &[(f64, f64)], x_range: ops::Range<f64>, buckets: u16) -> Vec<Option<f64>> {
+    let mut sums = vec![(0., 0usize); usize::from(buckets)];
+    let bucket_width = (x_range.end - x_range.start) / f64::from(buckets);
+    for &(x, y) in points {
+        let bucket = ((x - x_range.start) / bucket_width).max(0.) as usize;
+        if let Some((sum, count)) = sums.get_mut(bucket) {
+            *sum += y;
+            *count += 1;
+        }
+    }
+    sums.into_iter().map(|(sum, count)| (count > 0).then(|| sum / count as f64)).collect()
+}
+
+#[allow(clippy::cast_sign_loss)]
+fn gradient(min: f64, max: f64, value: f64) -> style::Color {
+    let ratio = if max > min { ((value - min) / (max - min)).clamp(0., 1.) } else { 0.5 };
+    let position = ratio * (GRADIENT.len() - 1) as f64;
+    let index = (position as usize).min(GRADIENT.len() - 2);
+    let frac = position - index as f64;
+
+    let [r, g, b] = [0, 1, 2].map(|channel| {
+        let start = f64::from(GRADIENT[index][channel]);
+        let end = f64::from(GRADIENT[index + 1][channel]);
+        (start + (end - start) * frac) as u8
+    });
+    style::Color::Rgb(r, g, b)
+}
@@ -0,0 +1,109 @@
+use std::iter;
+
+use plotters::coord;
+use plotters::element::{Cross, Pixel};
+use plotters::prelude::{ChartBuilder, DrawingArea};
+use plotters::style::{IntoTextStyle, RGBColor};
+use plotters_ratatui_backend::{AreaResult, Draw, RatatuiBackend, CHAR_PIXEL_SIZE};
+
+use super::DrawTarget;
+use crate::ui::theme::{self, Theme};
+use crate::util::disp_float;
+
+/// Fraction of the time window at the end of which points are highlighted as recent.
+const RECENT_RATIO: f64 = 0.1;
+
+/// Draws visible series against the series `x_label` instead of time as scatter points.
+pub(super) struct XyDrawImpl<'t> {
+    pub(super) targets: &'t [DrawTarget],
+    pub(super) x_label: &'t str,
+    /// The time window in negative seconds, used to determine recent points.
+    pub(super) time:    (f64, f64),
+    pub(super) theme:   &'t Theme,
+}
+
+struct XyPoint {
+    x:      f64,
+    y:      f64,
+    recent: bool,
+}
+
+impl XyDrawImpl<'_> {
+    /// Pairs each point of `target` with the latest point of the x series at or before its time.
+    fn pair(&self, x_points: &[(f64, f64)], target: &DrawTarget) -> Vec<XyPoint> {
+        let recent_since = self.time.1 - (self.time.1 - self.time.0) * RECENT_RATIO;
+
+        target
+            .points
+            .iter()
+            .filter_map(|&(time, y)| {
+                let index = x_points.partition_point(|&(x_time, _)| x_time <= time);
+                let (_, x) = x_points[index.checked_sub(1)?];
+                Some(XyPoint { x, y, recent: time >= recent_since })
+            })
+            .collect()
+    }
+}
+
+impl Draw for XyDrawImpl<'_> {
+    fn draw(&self, area: DrawingArea<RatatuiBackend, coord::Shift>) -> AreaResult {
+        let Some(x_target) = self.targets.iter().find(|target| target.label == self.x_label) else {
+            return Ok(());
+        };
+
+        let pairs: Vec<_> = self
+            .targets
+            .iter()
+            .filter(|target| target.visible && target.label != self.x_label)
+            .map(|target| (target.color, self.pair(&x_target.points, target)))
+            .collect();
+
+        let extrema = |values: &mut dyn Iterator<Item = f64>| {
+            values
+                .fold(None::<(f64, f64)>, |extrema, value| {
+                    let (min, max) = extrema.unwrap_or((value, value));
+                    Some((min.min(value), max.max(value)))
+                })
+                .unwrap_or((0.0, 1.0))
+        };
+        let x_extrema = extrema(&mut pairs.iter().flat_map(|(_, points)| points).map(|p| p.x));
+        let y_extrema = extrema(&mut pairs.iter().flat_map(|(_, points)| points).map(|p| p.y));
+
+        let mut chart = ChartBuilder::on(&area)
+            .margin_left(24)
+            .margin_bottom(12)
+            .set_left_and_bottom_label_area_size(1)
+            .build_cartesian_2d(x_extrema.0..x_extrema.1, y_extrema.0..y_extrema.1)?;
+
+        for ([color_r, color_g, color_b], points) in &pairs {
+            let color = RGBColor(*color_r, *color_g, *color_b);
+            let dim = RGBColor(color_r / 2, color_g / 2, color_b / 2);
+
+            chart.draw_series(
+                points
+                    .iter()
+                    .filter(|point| !point.recent)
+                    .map(|point| Pixel::new((point.x, point.y), dim)),
+            )?;
+            chart.draw_series(
+                points
+                    .iter()
+                    .filter(|point| point.recent)
+                    .map(|point| Cross::new((point.x, point.y), 1, color)),
+            )?;
+            if let Some(last) = points.last() {
+                chart.draw_series(iter::once(Cross::new((last.x, last.y), 3, color)))?;
+            }
+        }
+
+        chart
+            .configure_mesh()
+            .disable_mesh()
+            .axis_style(theme::plot(self.theme.axis))
+            .label_style(("", CHAR_PIXEL_SIZE).with_color(theme::plot(self.theme.axis)))
+            .x_label_formatter(&|&value| disp_float(value, 3))
+            .draw()?;
+
+        Ok(())
+    }
+}
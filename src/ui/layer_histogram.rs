@@ -0,0 +1,164 @@
+use std::iter;
+
+use anyhow::Result;
+use crossterm::event::Event;
+use plotters::coord;
+use plotters::element::{PathElement, Polygon, Text};
+use plotters::prelude::{ChartBuilder, DrawingArea};
+use plotters::style::{IntoFont as _, IntoTextStyle, RGBColor};
+use plotters_ratatui_backend::{AreaResult, Draw, PlottersWidget, RatatuiBackend, CHAR_PIXEL_SIZE};
+use ratatui::style::{Style, Stylize as _};
+use ratatui::{layout, widgets};
+
+use super::keymap::{Action, Scope};
+use super::theme::{self, Theme};
+use super::{Context, HandleInput, LayerCommand, LayerTrait};
+use crate::util::{center_subrect, disp_float};
+
+const PERCENTILES: &[(&str, f64)] = &[("p50", 0.5), ("p90", 0.9), ("p99", 0.99)];
+
+/// Displays the value distribution of a series over the visible time window.
+pub struct LayerHistogram {
+    label: String,
+}
+
+impl LayerHistogram {
+    pub fn new(label: String) -> Self { Self { label } }
+}
+
+struct DrawImpl<'t> {
+    /// Sorted values of the series.
+    values: &'t [f64],
+    bins:   usize,
+    color:  RGBColor,
+    theme:  &'t Theme,
+}
+
+impl Draw for DrawImpl<'_> {
+    #[allow(clippy::cast_sign_loss)]
+    fn draw(&self, area: DrawingArea<RatatuiBackend, coord::Shift>) -> AreaResult {
+        let (Some(&min), Some(&max)) = (self.values.first(), self.values.last()) else {
+            return Ok(());
+        };
+        let (min, max) = if min < max { (min, max) } else { (min - 0.5, max + 0.5) };
+
+        let bin_width = (max - min) / self.bins as f64;
+        let mut counts = vec![0usize; self.bins];
+        for &value in self.values {
+            let bin = (((value - min) / bin_width) as usize).min(self.bins - 1);
+            counts[bin] += 1;
+        }
+        let max_count = counts.iter().copied().max().unwrap_or(0).max(1);
+
+        let mut chart = ChartBuilder::on(&area)
+            .margin_left(24)
+            .margin_bottom(12)
+            .set_left_and_bottom_label_area_size(1)
+            .build_cartesian_2d(min..max, 0.0..max_count as f64)?;
+
+        chart.draw_series(counts.iter().enumerate().map(|(bin, &count)| {
+            let start = min + bin_width * bin as f64;
+            let end = start + bin_width;
+            Polygon::new(
+                [(start, 0.), (start, count as f64), (end, count as f64), (end, 0.)],
+                self.color,
+            )
+        }))?;
+
+        for (i, &(name, ratio)) in PERCENTILES.iter().enumerate() {
+            let value = percentile(self.values, ratio);
+            let label_y = max_count as f64 * (1. - 0.1 * i as f64);
+            chart.draw_series(iter::once(PathElement::new(
+                [(value, 0.), (value, max_count as f64)],
+                theme::plot(self.theme.marker),
+            )))?;
+            chart.draw_series(iter::once(Text::new(
+                format!("{name}={}", disp_float(value, 3)),
+                (value, label_y),
+                ("", CHAR_PIXEL_SIZE).into_font().color(&theme::plot(self.theme.marker)),
+            )))?;
+        }
+
+        chart
+            .configure_mesh()
+            .disable_mesh()
+            .axis_style(theme::plot(self.theme.axis))
+            .label_style(("", CHAR_PIXEL_SIZE).with_color(theme::plot(self.theme.axis)))
+            .x_label_formatter(&|&value| disp_float(value, 3))
+            .y_label_formatter(&|&value| format!("{value:.0}"))
+            .draw()?;
+
+        Ok(())
+    }
+}
+
+/// Computes the percentile of sorted values with the nearest-rank method.
+#[allow(clippy::cast_sign_loss)]
+fn percentile(sorted: &[f64], ratio: f64) -> f64 {
+    let rank = (ratio * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+impl LayerTrait for LayerHistogram {
+    fn render(&mut self, context: &mut Context, frame: &mut ratatui::Frame) {
+        let rect = center_subrect(frame.area(), (7, 10));
+        frame.render_widget(widgets::Clear, rect);
+
+        let target =
+            context.current_targets.iter().flatten().find(|target| target.label == self.label);
+
+        let mut values: Vec<f64> =
+            target.iter().flat_map(|target| &target.points).map(|&(_, y)| y).collect();
+        values.sort_by(f64::total_cmp);
+
+        let block = widgets::Block::default()
+            .title(format!(
+                "Distribution of {} (n={}, bins={})",
+                self.label,
+                values.len(),
+                context.histogram_bins
+            ))
+            .borders(widgets::Borders::all())
+            .border_style(Style::default().bold());
+        let inner = block.inner(rect);
+        frame.render_widget(block, rect);
+
+        let [color_r, color_g, color_b] = target.map_or([255; 3], |target| target.color);
+        let chart = PlottersWidget {
+            draw:          DrawImpl {
+                values: &values,
+                bins:   context.histogram_bins,
+                color:  RGBColor(color_r, color_g, color_b),
+                theme:  &context.theme,
+            },
+            error_handler: |err| {
+                context.warning_sender.clone().send(format!("Plotting error: {err:?}"));
+            },
+        };
+        frame.render_widget(chart, inner);
+    }
+
+    fn handle_input(
+        &mut self,
+        context: &mut Context,
+        event: &Event,
+        layer_cmds: &mut Vec<LayerCommand>,
+        _frame_size: layout::Rect,
+    ) -> Result<HandleInput> {
+        Ok(match context.keymap.action(Scope::Distribution, event) {
+            Some(Action::CloseDistribution) => {
+                layer_cmds.push(LayerCommand::Remove);
+                HandleInput::Consumed
+            }
+            Some(Action::FewerBins) => {
+                context.histogram_bins = context.histogram_bins.saturating_sub(1).max(1);
+                HandleInput::Consumed
+            }
+            Some(Action::MoreBins) => {
+                context.histogram_bins += 1;
+                HandleInput::Consumed
+            }
+            _ => HandleInput::Fallthru,
+        })
+    }
+}
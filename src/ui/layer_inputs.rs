@@ -0,0 +1,149 @@
+use std::time::SystemTime;
+
+use anyhow::Result;
+use crossterm::event::Event;
+use ratatui::style::{Style, Stylize as _};
+use ratatui::{layout, text, widgets};
+
+use super::keymap::{Action, Scope};
+use super::layer_prompt::{LayerPrompt, PromptAction};
+use super::{Context, HandleInput, Layer, LayerCommand, LayerTrait};
+use crate::input::State;
+use crate::util::center_subrect;
+
+/// Lists the state and activity of every input,
+/// and manages inputs at runtime.
+#[derive(Default)]
+pub struct LayerInputs {
+    selected: usize,
+}
+
+impl LayerTrait for LayerInputs {
+    fn render(&mut self, context: &mut Context, frame: &mut ratatui::Frame) {
+        let rect = center_subrect(frame.area(), (8, 10));
+        frame.render_widget(widgets::Clear, rect);
+
+        let now = SystemTime::now();
+        let theme = &context.theme;
+        let statuses = context.opener.statuses();
+        self.selected = self.selected.min(statuses.len().saturating_sub(1));
+        let rows = statuses.iter().map(|status| {
+            let snapshot = status.snapshot();
+            let paused = snapshot.state == State::Running && status.is_paused();
+            let state_style = match snapshot.state {
+                State::Running if paused => Style::default().dim(),
+                State::Running => Style::default(),
+                State::Eof | State::Stopped => Style::default().dim(),
+                State::Errored | State::WaitingForFile => Style::default().fg(theme.alert),
+            };
+            let last_message = snapshot.last_message.map_or_else(String::new, |time| {
+                let age = now.duration_since(time).unwrap_or_default();
+                format!("{:.0}s ago", age.as_secs_f64())
+            });
+            let last_error = snapshot.last_error.map_or_else(String::new, |(_, error)| {
+                error.lines().next().unwrap_or_default().to_string()
+            });
+
+            widgets::Row::new([
+                text::Span::raw(status.kind.name()),
+                text::Span::raw(status.path.clone()),
+                text::Span::styled(
+                    if paused { "paused".to_string() } else { snapshot.state.to_string() },
+                    state_style,
+                ),
+                text::Span::raw(format!("{:.1}/s", snapshot.rate)),
+                text::Span::raw(snapshot.messages.to_string()),
+                text::Span::raw(last_message),
+                text::Span::raw(snapshot.series.to_string()),
+                text::Span::styled(last_error, Style::default().fg(theme.alert)),
+            ])
+        });
+
+        let mut state = widgets::TableState::default().with_selected(Some(self.selected));
+        frame.render_stateful_widget(
+            widgets::Table::new(
+                rows,
+                [
+                    layout::Constraint::Length(9),
+                    layout::Constraint::Fill(2),
+                    layout::Constraint::Length(16),
+                    layout::Constraint::Length(9),
+                    layout::Constraint::Length(8),
+                    layout::Constraint::Length(10),
+                    layout::Constraint::Length(6),
+                    layout::Constraint::Fill(3),
+                ],
+            )
+            .header(
+                widgets::Row::new([
+                    "Kind",
+                    "Path",
+                    "State",
+                    "Rate",
+                    "Messages",
+                    "Last",
+                    "Series",
+                    "Last error",
+                ])
+                .bold()
+                .bottom_margin(1),
+            )
+            .column_spacing(1)
+            .row_highlight_style(Style::default().bg(theme.focus))
+            .block(
+                widgets::Block::default()
+                    .title(format!("Inputs ({})", statuses.len()))
+                    .borders(widgets::Borders::all())
+                    .border_style(Style::default().bold()),
+            ),
+            rect,
+            &mut state,
+        );
+    }
+
+    fn handle_input(
+        &mut self,
+        context: &mut Context,
+        event: &Event,
+        layer_cmds: &mut Vec<LayerCommand>,
+        _frame_size: layout::Rect,
+    ) -> Result<HandleInput> {
+        if let Some(action) = context.keymap.action(Scope::Inputs, event) {
+            let statuses = context.opener.statuses();
+            let selected = statuses.get(self.selected);
+            match action {
+                Action::CloseInputs => layer_cmds.push(LayerCommand::Remove),
+                Action::PreviousInput => self.selected = self.selected.saturating_sub(1),
+                Action::NextInput => {
+                    self.selected = (self.selected + 1).min(statuses.len().saturating_sub(1));
+                }
+                Action::TogglePause => {
+                    if let Some(status) = selected {
+                        status.toggle_pause();
+                    }
+                }
+                Action::StopInput => {
+                    if let Some(status) = selected {
+                        context.opener.stop(status);
+                    }
+                }
+                Action::AddInput => {
+                    let prompt = LayerPrompt::new("Add input", PromptAction::Command)
+                        .with_input("add-input ".into());
+                    layer_cmds.push(LayerCommand::Push(Layer::Prompt(prompt)));
+                }
+                Action::RunCommand => {
+                    let prompt = LayerPrompt::new("Command", PromptAction::RunCommand);
+                    layer_cmds.push(LayerCommand::Push(Layer::Prompt(prompt)));
+                }
+                _ => return Ok(HandleInput::Fallthru),
+            }
+            return Ok(HandleInput::Consumed);
+        }
+        Ok(match context.keymap.action(Scope::Main, event) {
+            // Do not allow opening multiple input panels
+            Some(Action::ShowInputs) => HandleInput::Consumed,
+            _ => HandleInput::Fallthru,
+        })
+    }
+}
@@ -1,13 +1,23 @@
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
 use std::iter;
 
 use anyhow::Result;
-use crossterm::event::{self, Event, KeyEvent};
+use clap::ValueEnum as _;
+use crossterm::event::Event;
 use ratatui::style::{Style, Stylize};
 use ratatui::text::Text;
-use ratatui::{layout, style, widgets};
+use ratatui::{layout, style, text, widgets};
+use serde::{Deserialize, Serialize};
 
-use super::data::DisplayConfig;
-use super::{Context, HandleInput, LayerCommand, LayerTrait};
+use super::data::{DisplayConfig, SeriesFilter};
+use super::group::{Aggregate, GroupPath, Grouping};
+use super::keymap::{Action, Scope};
+use super::layer_chart::DrawTarget;
+use super::layer_histogram::LayerHistogram;
+use super::layer_prompt::{LayerPrompt, PromptAction};
+use super::session::LegendState;
+use super::{Context, HandleInput, Layer, LayerCommand, LayerTrait, Options};
 use crate::util::{
     self, disp_float, AnchoredPosition, Gravity, SaturatingAddExt, SaturatingSubExt,
 };
@@ -15,13 +25,57 @@ use crate::util::{
 pub struct LayerLegend {
     position:       AnchoredPosition,
     layer_focused:  bool,
-    series_focus:   Option<String>,
+    focus:          Option<RowKey>,
     changing_color: bool,
     last_dim:       (u16, u16),
+    view:           LegendView,
+    collapsed:      BTreeSet<GroupPath>,
+    /// The listed rows in display order, as of the last render.
+    rows:           Vec<RowKey>,
+    /// Index of the first listed row in view.
+    scroll:         usize,
 }
 
-impl Default for LayerLegend {
-    fn default() -> Self {
+/// Identifies a row of the legend.
+#[derive(Clone, PartialEq, Eq)]
+enum RowKey {
+    Series(String),
+    Group(GroupPath),
+}
+
+/// Settings of which series are listed in the legend and how.
+struct LegendView {
+    sort:       LegendSort,
+    compact:    bool,
+    /// Whether series without points in the time window are listed.
+    show_empty: bool,
+}
+
+/// The order of series in the legend.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LegendSort {
+    /// Alphabetical order of labels.
+    #[default]
+    Name,
+    /// Descending order of the latest value in the time window.
+    LastValue,
+    /// Descending order of the maximum value in the time window.
+    Max,
+}
+
+impl LegendSort {
+    fn next(self) -> Self {
+        match self {
+            Self::Name => Self::LastValue,
+            Self::LastValue => Self::Max,
+            Self::Max => Self::Name,
+        }
+    }
+}
+
+impl LayerLegend {
+    pub fn new(options: &Options) -> Self {
         Self {
             position:       AnchoredPosition {
                 anchor:     Gravity::TOP | Gravity::RIGHT,
@@ -29,9 +83,352 @@ impl Default for LayerLegend {
                 y_displace: 0,
             },
             layer_focused:  false,
-            series_focus:   None,
+            focus:          None,
             changing_color: false,
             last_dim:       (0, 0),
+            view:           LegendView {
+                sort:       options.legend_sort,
+                compact:    options.legend_compact,
+                show_empty: options.legend_show_empty,
+            },
+            collapsed:      BTreeSet::new(),
+            rows:           Vec::new(),
+            scroll:         0,
+        }
+    }
+
+    pub fn session_state(&self, context: &Context) -> LegendState {
+        LegendState {
+            anchor_right:  self.position.anchor.contains(Gravity::RIGHT),
+            anchor_bottom: self.position.anchor.contains(Gravity::BOTTOM),
+            x_displace:    self.position.x_displace,
+            y_displace:    self.position.y_displace,
+            sort:          self.view.sort,
+            compact:       self.view.compact,
+            show_empty:    self.view.show_empty,
+            filter:        context.series_filter.as_ref().map(|filter| filter.text.clone()),
+            collapsed:     self.collapsed.iter().cloned().collect(),
+        }
+    }
+
+    pub fn restore_session(&mut self, context: &mut Context, state: &LegendState) {
+        let mut anchor = Gravity::LEFT | Gravity::TOP;
+        anchor.set(Gravity::RIGHT, state.anchor_right);
+        anchor.set(Gravity::BOTTOM, state.anchor_bottom);
+        self.position =
+            AnchoredPosition { anchor, x_displace: state.x_displace, y_displace: state.y_displace };
+        self.view = LegendView {
+            sort:       state.sort,
+            compact:    state.compact,
+            show_empty: state.show_empty,
+        };
+        self.collapsed = state.collapsed.iter().cloned().collect();
+        context.series_filter = state.filter.clone().map(SeriesFilter::new);
+    }
+
+    /// Returns the labels of the focused series, or of all series in the focused group.
+    fn focused_labels(&self, context: &Context, action: &str) -> Option<Vec<String>> {
+        match &self.focus {
+            None => {
+                let hint = select_hint(context, "series");
+                context.warning_sender.clone().send(format!("{hint} to {action}"));
+                None
+            }
+            Some(RowKey::Series(label)) => Some(vec![label.clone()]),
+            Some(RowKey::Group(group)) => Some(
+                context
+                    .cache
+                    .disp_config
+                    .keys()
+                    .filter(|label| context.grouping.contains(&context.cache, group, label))
+                    .cloned()
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Returns the label of the focused series, warning if a group or nothing is focused.
+    fn focused_series(&self, context: &Context, action: &str) -> Option<String> {
+        if let Some(RowKey::Series(label)) = &self.focus {
+            return Some(label.clone());
+        }
+        let hint = select_hint(context, "series");
+        context.warning_sender.clone().send(format!("{hint} to {action}"));
+        None
+    }
+
+    fn focused_configs<'t>(
+        &self,
+        context: &'t mut Context,
+        action: &str,
+    ) -> Vec<&'t mut DisplayConfig> {
+        let labels = self.focused_labels(context, action).unwrap_or_default();
+        context
+            .cache
+            .disp_config
+            .iter_mut()
+            .filter(|(label, _)| labels.contains(label))
+            .map(|(_, config)| config)
+            .collect()
+    }
+
+    fn move_focus(&mut self, forward: bool) {
+        let rows = &self.rows;
+
+        self.focus = if rows.is_empty() {
+            None
+        } else {
+            let new_index = match &self.focus {
+                None if forward => 0,
+                None => rows.len() - 1,
+                Some(key) => {
+                    let current_index = rows.iter().position(|row| row == key).unwrap_or(0);
+                    if forward {
+                        (current_index + 1) % rows.len()
+                    } else {
+                        (current_index + rows.len() - 1) % rows.len()
+                    }
+                }
+            };
+            rows.get(new_index).cloned()
+        };
+    }
+
+    /// Adjusts the scroll offset to keep the focused series within `page_size` rows in view.
+    fn scroll_to_focus(&mut self, page_size: usize) {
+        if let Some(index) =
+            self.focus.as_ref().and_then(|focus| self.rows.iter().position(|row| row == focus))
+        {
+            if index < self.scroll {
+                self.scroll = index;
+            } else if index >= self.scroll + page_size {
+                self.scroll = index + 1 - page_size;
+            }
+        }
+        self.scroll = self.scroll.min(self.rows.len().saturating_sub(page_size));
+    }
+
+    fn change_color(&mut self, context: &mut Context, action: Action) {
+        self.changing_color = false;
+
+        let random = rand::random::<[u8; 3]>();
+        for DisplayConfig { color, .. } in self.focused_configs(context, "change its color") {
+            match action {
+                Action::MoreRed => color[0].saturating_add_assign(15),
+                Action::LessRed => color[0].saturating_sub_assign(15),
+                Action::MoreGreen => color[1].saturating_add_assign(15),
+                Action::LessGreen => color[1].saturating_sub_assign(15),
+                Action::MoreBlue => color[2].saturating_add_assign(15),
+                Action::LessBlue => color[2].saturating_sub_assign(15),
+                Action::RandomColor => *color = random,
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    /// Toggles the visibility or cycles the style of the focused series or group.
+    ///
+    /// All series in a group are changed to the same setting.
+    fn change_display(&self, context: &mut Context, action: Action) {
+        match action {
+            Action::ToggleVisibility => {
+                let mut configs = self.focused_configs(context, "toggle visibility");
+                let any_visible = configs.iter().any(|config| config.visible);
+                for config in &mut configs {
+                    config.visible = !any_visible;
+                }
+            }
+            Action::CycleStyle => {
+                let mut configs = self.focused_configs(context, "change its style");
+                if let Some(style) = configs.first().map(|config| config.style.next()) {
+                    for config in &mut configs {
+                        config.style = style;
+                    }
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Collapses or expands the focused group, or collapses the group of the focused series.
+    fn toggle_group(&mut self, context: &mut Context) {
+        match self.focus.clone() {
+            Some(RowKey::Group(group)) => {
+                if self.collapsed.remove(&group) {
+                    context.aggregates.remove(&group);
+                } else {
+                    self.collapsed.insert(group);
+                }
+            }
+            Some(RowKey::Series(label)) => {
+                let (group, _) = context.grouping.path(&context.cache, &label);
+                if !group.is_empty() {
+                    self.collapsed.insert(group.clone());
+                    self.focus = Some(RowKey::Group(group));
+                }
+            }
+            None => {
+                let hint = select_hint(context, "series");
+                context.warning_sender.send(format!("{hint} to collapse its group"));
+            }
+        }
+    }
+
+    fn cycle_aggregate(&mut self, context: &mut Context) {
+        let Some(RowKey::Group(group)) = &self.focus else {
+            let hint = select_hint(context, "group");
+            context.warning_sender.send(format!("{hint} to aggregate it"));
+            return;
+        };
+        match Aggregate::next(context.aggregates.get(group).copied()) {
+            Some(aggregate) => {
+                self.collapsed.insert(group.clone());
+                context.aggregates.insert(group.clone(), aggregate);
+            }
+            None => {
+                context.aggregates.remove(group);
+            }
+        }
+    }
+
+    /// Lists the rows of `nodes` in display order, skipping the descendants of collapsed groups.
+    fn flatten(
+        &self,
+        context: &Context,
+        targets: &[DrawTarget],
+        nodes: &[Node<'_>],
+        path: &mut GroupPath,
+        contents: &mut Vec<RowContent>,
+    ) {
+        let indent = "  ".repeat(path.len());
+        for node in nodes {
+            match node {
+                Node::Series { name, target } => contents.push(RowContent {
+                    key:     RowKey::Series(target.label.clone()),
+                    label:   match context.grouping {
+                        Grouping::Flat => target.label.clone(),
+                        Grouping::Separator(_) | Grouping::Tags(_) => format!("{indent}{name}"),
+                    },
+                    color:   target.color,
+                    visible: target.visible,
+                    group:   false,
+                    value:   target
+                        .points
+                        .last()
+                        .map_or_else(|| "-".into(), |&(_, y)| disp_float(y, 4)),
+                }),
+                Node::Group { name, children } => {
+                    path.push(name.clone());
+                    let collapsed = self.collapsed.contains(path);
+
+                    let mut leaves = Vec::new();
+                    collect_leaves(children, &mut leaves);
+                    let value = match context.aggregates.get(path) {
+                        Some(aggregate) => targets
+                            .iter()
+                            .find(|target| target.group.as_ref() == Some(path))
+                            .and_then(|target| target.points.last())
+                            .map_or_else(
+                                || format!("- ({})", aggregate.name()),
+                                |&(_, y)| format!("{} ({})", disp_float(y, 4), aggregate.name()),
+                            ),
+                        None => format!("[{}]", leaves.len()),
+                    };
+                    contents.push(RowContent {
+                        key: RowKey::Group(path.clone()),
+                        label: format!("{indent}{} {name}", if collapsed { '▸' } else { '▾' }),
+                        color: leaves.first().map_or([255; 3], |target| target.color),
+                        visible: leaves.iter().any(|target| {
+                            context
+                                .cache
+                                .disp_config
+                                .get(&target.label)
+                                .is_some_and(|config| config.visible)
+                        }),
+                        group: true,
+                        value,
+                    });
+
+                    if !collapsed {
+                        self.flatten(context, targets, children, path, contents);
+                    }
+                    path.pop();
+                }
+            }
+        }
+    }
+
+    fn render_row(&self, content: RowContent) -> (widgets::Row<'static>, [usize; 2]) {
+        const COMPACT_LABEL_WIDTH: usize = 16;
+
+        let RowContent {
+            key,
+            mut label,
+            color: [color_r, color_g, color_b],
+            visible,
+            group,
+            value,
+        } = content;
+        if self.view.compact && label.chars().count() > COMPACT_LABEL_WIDTH {
+            label = label.chars().take(COMPACT_LABEL_WIDTH - 1).chain(['…']).collect();
+        }
+        let value = if self.view.compact { String::new() } else { value };
+        let widths = [label.chars().count(), value.chars().count()];
+
+        let mut base_style = Style::default();
+        if self.focus.as_ref() == Some(&key) {
+            base_style = base_style.underlined();
+        }
+        if !visible {
+            base_style = base_style.crossed_out().italic();
+        }
+        if group {
+            base_style = base_style.bold();
+        }
+
+        let row = widgets::Row::new([
+            Text::styled(label, base_style.fg(style::Color::Rgb(color_r, color_g, color_b))),
+            Text::styled(value, base_style),
+        ]);
+        (row, widths)
+    }
+
+    fn change_view(&mut self, action: Action) {
+        let view = &mut self.view;
+        match action {
+            Action::CycleSort => view.sort = view.sort.next(),
+            Action::ToggleCompact => view.compact = !view.compact,
+            Action::ToggleEmpty => view.show_empty = !view.show_empty,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Sets the visibility of the series matching the filter, and of the others if `solo`.
+    ///
+    /// Without a filter, `solo` shows only the focused series or group.
+    fn set_matching_visible(&self, context: &mut Context, visible: bool, solo: bool) {
+        let focus = if solo && context.series_filter.is_none() {
+            let Some(labels) = self.focused_labels(context, "solo it, or search with `/`,") else {
+                return;
+            };
+            Some(labels)
+        } else {
+            None
+        };
+
+        for (label, config) in &mut context.cache.disp_config {
+            let matches = match &focus {
+                Some(focus) => focus.contains(label),
+                None => context
+                    .series_filter
+                    .as_ref()
+                    .is_none_or(|filter| filter.matches(label, context.cache.keys.get(label))),
+            };
+            if matches {
+                config.visible = visible;
+            } else if solo {
+                config.visible = !visible;
+            }
         }
     }
 }
@@ -39,31 +436,44 @@ impl Default for LayerLegend {
 impl LayerTrait for LayerLegend {
     fn render(&mut self, context: &mut Context, frame: &mut ratatui::Frame) {
         let Some(targets) = &context.current_targets else { return };
-        let (rows, max_widths): (Vec<_>, [usize; 2]) = targets
+
+        let mut entries: Vec<_> = targets
             .iter()
-            .filter_map(|target| {
-                let [color_r, color_g, color_b] = target.color;
+            .filter(|target| target.group.is_none())
+            .filter(|target| filter_matches(context, &target.label))
+            .filter(|target| self.view.show_empty || !target.points.is_empty())
+            .collect();
+        match self.view.sort {
+            LegendSort::Name => {}
+            LegendSort::LastValue => entries.sort_by(|a, b| {
+                let last = |target: &DrawTarget| target.points.last().map(|&(_, y)| y);
+                last(b).partial_cmp(&last(a)).unwrap_or(Ordering::Equal)
+            }),
+            LegendSort::Max => entries.sort_by(|a, b| {
+                let max =
+                    |target: &DrawTarget| target.points.iter().map(|&(_, y)| y).reduce(f64::max);
+                max(b).partial_cmp(&max(a)).unwrap_or(Ordering::Equal)
+            }),
+        }
 
-                let last_value = disp_float(target.points.last().map(|&(_, y)| y)?, 4);
-                let widths = [target.label.len(), last_value.len()];
+        let mut tree = Vec::new();
+        for &target in &entries {
+            let (groups, name) = context.grouping.path(&context.cache, &target.label);
+            insert_node(&mut tree, &groups, name, target);
+        }
+        let mut contents = Vec::new();
+        self.flatten(context, targets, &tree, &mut Vec::new(), &mut contents);
+        self.rows = contents.iter().map(|content| content.key.clone()).collect();
 
-                let mut base_style = Style::default();
-                if self.series_focus.as_ref().is_some_and(|name| name == &target.label) {
-                    base_style = base_style.underlined();
-                }
-                if !target.visible {
-                    base_style = base_style.crossed_out().italic();
-                }
+        if let Some(focus) = &self.focus {
+            if !self.rows.contains(focus) {
+                self.focus = self.rows.first().cloned();
+            }
+        }
 
-                let row = widgets::Row::new([
-                    Text::styled(
-                        target.label.clone(),
-                        base_style.fg(style::Color::Rgb(color_r, color_g, color_b)),
-                    ),
-                    Text::styled(last_value, base_style),
-                ]);
-                Some((row, widths))
-            })
+        let (rows, max_widths): (Vec<_>, [usize; 2]) = contents
+            .into_iter()
+            .map(|content| self.render_row(content))
             .fold((Vec::new(), [0, 0]), |(mut rows, mut max_widths), (row, widths)| {
                 rows.push(row);
                 for (max_width, width) in iter::zip(&mut max_widths, widths) {
@@ -72,148 +482,232 @@ impl LayerTrait for LayerLegend {
                 (rows, max_widths)
             });
 
-        let table_width = (max_widths[0] + max_widths[1] + 1) as u16 + 2;
-        let table_height = rows.len() as u16 + 2;
+        let bounds = frame.area().inner(layout::Margin { horizontal: 5, vertical: 2 });
+        let page_size = usize::from(bounds.height.saturating_sub(2)).max(1).min(rows.len());
+        self.scroll_to_focus(page_size);
+
+        let title = legend_title(context, self.view.sort, entries.len(), targets.len());
+        let title_width = title.iter().map(text::Span::width).sum::<usize>();
+        let value_width = if self.view.compact { 0 } else { max_widths[1] + 1 };
+        let table_width = (max_widths[0] + value_width).max(title_width) as u16 + 2;
+        let table_height = page_size as u16 + 2;
         self.last_dim = (table_width, table_height);
 
-        let rect = self.position.to_rect(
-            table_width,
-            table_height,
-            frame.area().inner(layout::Margin { horizontal: 5, vertical: 2 }),
-        );
+        let rect = self.position.to_rect(table_width, table_height, bounds);
 
         let mut border_style = Style::default();
         if self.layer_focused {
-            border_style = border_style.on_black();
+            border_style = border_style.bg(context.theme.focus);
         }
 
+        let total_rows = rows.len();
         frame.render_widget(
             widgets::Table::default()
-                .rows(rows)
+                .rows(rows.into_iter().skip(self.scroll).take(page_size))
                 .widths(max_widths.map(|width| layout::Constraint::Length(width as u16)))
-                .column_spacing(1)
+                .column_spacing(u16::from(!self.view.compact))
                 .block(
                     widgets::Block::default()
-                        .title("Legend")
+                        .title(title)
                         .borders(widgets::Borders::all())
                         .border_style(border_style),
                 ),
             rect,
         );
+
+        if total_rows > page_size {
+            let mut state =
+                widgets::ScrollbarState::new(total_rows - page_size).position(self.scroll);
+            frame.render_stateful_widget(
+                widgets::Scrollbar::new(widgets::ScrollbarOrientation::VerticalRight),
+                rect.inner(layout::Margin { vertical: 1, horizontal: 0 }),
+                &mut state,
+            );
+        }
     }
 
     fn handle_input(
         &mut self,
         context: &mut Context,
         event: &Event,
-        _layer_cmds: &mut Vec<LayerCommand>,
+        layer_cmds: &mut Vec<LayerCommand>,
         frame_size: layout::Rect,
     ) -> Result<HandleInput> {
         if self.changing_color {
-            if let &Event::Key(KeyEvent {
-                code: event::KeyCode::Char(key @ ('r' | 'R' | 'g' | 'G' | 'b' | 'B' | 'c')),
-                ..
-            }) = event
-            {
-                self.changing_color = false;
-
-                let Some(name) = self.series_focus.as_deref() else {
-                    context.warning_sender.send(String::from(
-                        "Cannot change color code because no series is selected",
-                    ));
-                    return Ok(HandleInput::Consumed);
-                };
-
-                let DisplayConfig { color, .. } = context
-                    .cache
-                    .disp_config
-                    .get_mut(name)
-                    .expect("existing series name should have corresponding color entry");
-                match key {
-                    'r' => color[0].saturating_add_assign(15),
-                    'R' => color[0].saturating_sub_assign(15),
-                    'g' => color[1].saturating_add_assign(15),
-                    'G' => color[1].saturating_sub_assign(15),
-                    'b' => color[2].saturating_add_assign(15),
-                    'B' => color[2].saturating_sub_assign(15),
-                    'c' => *color = rand::random::<[u8; 3]>(),
-                    _ => unreachable!(),
-                }
-
+            if let Some(action) = context.keymap.action(Scope::LegendColor, event) {
+                self.change_color(context, action);
                 return Ok(HandleInput::Consumed);
             }
         }
 
-        Ok(match event {
-            Event::Key(KeyEvent { code: event::KeyCode::Char('g'), .. }) => {
+        let Some(action) = context.keymap.action(Scope::Legend, event) else {
+            return Ok(HandleInput::Fallthru);
+        };
+
+        Ok(match action {
+            Action::FocusLegend => {
                 self.layer_focused = !self.layer_focused;
                 HandleInput::Consumed
             }
+            Action::Search => {
+                self.layer_focused = true;
+                let current = context.series_filter.as_ref().map(|filter| filter.text.clone());
+                let prompt =
+                    LayerPrompt::new("/", PromptAction::Filter { previous: current.clone() })
+                        .with_input(current.unwrap_or_default());
+                layer_cmds.push(LayerCommand::Push(Layer::Prompt(prompt)));
+                HandleInput::Consumed
+            }
             _ if !self.layer_focused => HandleInput::Fallthru,
-            &Event::Key(KeyEvent {
-                code: event::KeyCode::Char(key @ ('H' | 'J' | 'K' | 'L')),
-                ..
-            }) => {
-                let dir = match key {
-                    'H' => util::Direction::Left,
-                    'L' => util::Direction::Right,
-                    'K' => util::Direction::Top,
-                    'J' => util::Direction::Bottom,
+            Action::ShowMatching | Action::HideMatching => {
+                self.set_matching_visible(context, action == Action::ShowMatching, false);
+                HandleInput::Consumed
+            }
+            Action::Solo => {
+                self.set_matching_visible(context, true, true);
+                HandleInput::Consumed
+            }
+            Action::MoveLegendLeft
+            | Action::MoveLegendRight
+            | Action::MoveLegendUp
+            | Action::MoveLegendDown => {
+                let dir = match action {
+                    Action::MoveLegendLeft => util::Direction::Left,
+                    Action::MoveLegendRight => util::Direction::Right,
+                    Action::MoveLegendUp => util::Direction::Top,
+                    Action::MoveLegendDown => util::Direction::Bottom,
                     _ => unreachable!(),
                 };
                 self.position.move_towards(dir, 5);
                 self.position.anchor_by_nearest(self.last_dim.0, self.last_dim.1, frame_size);
                 HandleInput::Consumed
             }
-            &Event::Key(KeyEvent { code: event::KeyCode::Char(' '), .. }) => {
-                let Some(name) = self.series_focus.as_deref() else {
-                    context
-                        .warning_sender
-                        .send(String::from("Select a series with `j`/`k` to toggle visibility"));
-                    return Ok(HandleInput::Consumed);
-                };
-                let DisplayConfig { visible, .. } = context
-                    .cache
-                    .disp_config
-                    .get_mut(name)
-                    .expect("existing series name should have corresponding color entry");
-                *visible = !*visible;
+            Action::ToggleVisibility | Action::CycleStyle => {
+                self.change_display(context, action);
                 HandleInput::Consumed
             }
-            &Event::Key(KeyEvent { code: event::KeyCode::Char('c'), .. }) => {
+            Action::ToggleXAxis => {
+                if let Some(name) = self.focused_series(context, "use it as the X axis") {
+                    context.x_series = match context.x_series.take() {
+                        Some(x_series) if x_series == name => None,
+                        _ => Some(name),
+                    };
+                }
+                HandleInput::Consumed
+            }
+            Action::ShowDistribution => {
+                if let Some(name) = self.focused_series(context, "view its distribution") {
+                    let histogram = LayerHistogram::new(name);
+                    layer_cmds.push(LayerCommand::Push(Layer::Histogram(histogram)));
+                }
+                HandleInput::Consumed
+            }
+            Action::ToggleGroup => {
+                self.toggle_group(context);
+                HandleInput::Consumed
+            }
+            Action::CycleAggregate => {
+                self.cycle_aggregate(context);
+                HandleInput::Consumed
+            }
+            Action::ChangeColor => {
                 self.changing_color = true;
                 HandleInput::Consumed
             }
-            &Event::Key(KeyEvent { code: event::KeyCode::Char(input @ ('j' | 'k')), .. }) => {
-                let series_names: Vec<_> = context.cache.data.map.keys().collect();
-
-                self.series_focus = if series_names.is_empty() {
-                    None
-                } else {
-                    let new_index = match self.series_focus.as_deref() {
-                        None => match input {
-                            'j' => 0,
-                            'k' => series_names.len() - 1,
-                            _ => unreachable!(),
-                        },
-                        Some(key) => {
-                            let current_index =
-                                series_names.iter().position(|name| *name == key).unwrap_or(0);
-                            match input {
-                                'j' => (current_index + 1) % series_names.len(),
-                                'k' => {
-                                    (current_index + series_names.len() - 1) % series_names.len()
-                                }
-                                _ => unreachable!(),
-                            }
-                        }
-                    };
-                    series_names.get(new_index).map(|string| (*string).to_string())
-                };
-
+            Action::CycleSort | Action::ToggleCompact | Action::ToggleEmpty => {
+                self.change_view(action);
+                HandleInput::Consumed
+            }
+            Action::PreviousSeries | Action::NextSeries => {
+                self.move_focus(action == Action::NextSeries);
                 HandleInput::Consumed
             }
             _ => HandleInput::Fallthru,
         })
     }
 }
+
+/// A node in the tree of listed series.
+enum Node<'t> {
+    Group { name: String, children: Vec<Node<'t>> },
+    Series { name: String, target: &'t DrawTarget },
+}
+
+/// Inserts a series into the tree under `groups`,
+/// creating groups in the order they are first seen.
+fn insert_node<'t>(
+    nodes: &mut Vec<Node<'t>>,
+    groups: &[String],
+    name: String,
+    target: &'t DrawTarget,
+) {
+    let Some((first, rest)) = groups.split_first() else {
+        nodes.push(Node::Series { name, target });
+        return;
+    };
+
+    let index = nodes
+        .iter()
+        .position(|node| matches!(node, Node::Group { name, .. } if name == first))
+        .unwrap_or_else(|| {
+            nodes.push(Node::Group { name: first.clone(), children: Vec::new() });
+            nodes.len() - 1
+        });
+    let Node::Group { children, .. } = &mut nodes[index] else { unreachable!() };
+    insert_node(children, rest, name, target);
+}
+
+fn collect_leaves<'t>(nodes: &[Node<'t>], leaves: &mut Vec<&'t DrawTarget>) {
+    for node in nodes {
+        match node {
+            Node::Group { children, .. } => collect_leaves(children, leaves),
+            Node::Series { target, .. } => leaves.push(target),
+        }
+    }
+}
+
+/// The displayed content of a row of the legend.
+struct RowContent {
+    key:     RowKey,
+    label:   String,
+    color:   [u8; 3],
+    visible: bool,
+    group:   bool,
+    value:   String,
+}
+
+fn legend_title(
+    context: &Context,
+    sort: LegendSort,
+    shown: usize,
+    total: usize,
+) -> Vec<text::Span<'static>> {
+    let mut title = vec![text::Span::raw("Legend")];
+    if sort != LegendSort::Name {
+        let name = sort.to_possible_value().expect("no skipped variants");
+        title.push(text::Span::raw(format!(" by {}", name.get_name())));
+    }
+    if let Some(filter) = &context.series_filter {
+        let mut style = Style::default().fg(context.theme.highlight);
+        if !filter.is_valid() {
+            style = style.fg(context.theme.alert);
+        }
+        title.push(text::Span::styled(format!(" /{} [{shown}/{total}]", filter.text), style));
+    }
+    title
+}
+
+fn filter_matches(context: &Context, label: &str) -> bool {
+    context
+        .series_filter
+        .as_ref()
+        .is_none_or(|filter| filter.matches(label, context.cache.key(label)))
+}
+
+fn select_hint(context: &Context, row: &str) -> String {
+    format!(
+        "Select a {row} with `{}`/`{}`",
+        context.keymap.keys_display(Action::NextSeries),
+        context.keymap.keys_display(Action::PreviousSeries),
+    )
+}
@@ -0,0 +1,142 @@
+use std::time::SystemTime;
+
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyEvent};
+use ratatui::style::Style;
+use ratatui::{layout, widgets};
+
+use super::data::SeriesFilter;
+use super::keymap::{Action, Scope};
+use super::{command, Context, HandleInput, LayerCommand, LayerTrait};
+
+/// A single-line text input that captures all key events until submitted or cancelled.
+pub struct LayerPrompt {
+    title:  &'static str,
+    input:  String,
+    action: PromptAction,
+    /// Candidates of the last ambiguous completion.
+    hint:   Vec<String>,
+}
+
+pub enum PromptAction {
+    /// Add a manual annotation marker at the given time.
+    Marker(SystemTime),
+    /// Run a command, see [`command::COMMANDS`].
+    Command,
+    /// Open an input reading JSON Lines from the entered shell command.
+    RunCommand,
+    /// Update the legend search on every key, restoring the previous search if cancelled.
+    Filter { previous: Option<String> },
+}
+
+impl LayerPrompt {
+    pub fn new(title: &'static str, action: PromptAction) -> Self {
+        Self { title, input: String::new(), action, hint: Vec::new() }
+    }
+
+    pub fn with_input(mut self, input: String) -> Self {
+        self.input = input;
+        self
+    }
+
+    fn edited(&mut self, context: &mut Context) {
+        self.hint.clear();
+        if let PromptAction::Filter { .. } = self.action {
+            set_filter(context, self.input.clone());
+        }
+    }
+
+    fn cancel(&mut self, context: &mut Context) {
+        if let PromptAction::Filter { previous } = &mut self.action {
+            set_filter(context, previous.take().unwrap_or_default());
+        }
+    }
+
+    fn complete(&mut self, context: &Context) {
+        if let PromptAction::Command = self.action {
+            (self.input, self.hint) = command::complete(context, &self.input);
+        }
+    }
+
+    fn submit(&mut self, context: &mut Context) {
+        let input = self.input.trim();
+        match self.action {
+            PromptAction::Marker(time) => {
+                if !input.is_empty() {
+                    context.cache.push_annotation(time, input.to_string());
+                }
+            }
+            PromptAction::Command => {
+                if let Err(err) = command::run(context, input) {
+                    context.warning_sender.send(format!("Error: {err:?}"));
+                }
+            }
+            PromptAction::RunCommand => {
+                if !input.is_empty() {
+                    command::open_inputs(context, vec![crate::input::command_spec(input)]);
+                }
+            }
+            PromptAction::Filter { .. } => {}
+        }
+    }
+}
+
+impl LayerTrait for LayerPrompt {
+    fn render(&mut self, context: &mut Context, frame: &mut ratatui::Frame) {
+        let area = frame.area();
+        let rect = layout::Rect {
+            x:      area.x,
+            y:      area.bottom().saturating_sub(3),
+            width:  area.width,
+            height: area.height.min(3),
+        };
+
+        let mut block = widgets::Block::default()
+            .title(self.title)
+            .borders(widgets::Borders::all())
+            .border_style(Style::default().bg(context.theme.focus));
+        if !self.hint.is_empty() {
+            block = block.title_bottom(self.hint.join(" "));
+        }
+
+        frame.render_widget(widgets::Clear, rect);
+        frame.render_widget(widgets::Paragraph::new(format!("{}_", self.input)).block(block), rect);
+    }
+
+    fn handle_input(
+        &mut self,
+        context: &mut Context,
+        event: &Event,
+        layer_cmds: &mut Vec<LayerCommand>,
+        _frame_size: layout::Rect,
+    ) -> Result<HandleInput> {
+        let Event::Key(KeyEvent { code, .. }) = event else { return Ok(HandleInput::Fallthru) };
+
+        match (context.keymap.action(Scope::Prompt, event), code) {
+            (Some(Action::Cancel), _) => {
+                self.cancel(context);
+                layer_cmds.push(LayerCommand::Remove);
+            }
+            (Some(Action::Submit), _) => {
+                self.submit(context);
+                layer_cmds.push(LayerCommand::Remove);
+            }
+            (Some(Action::Complete), _) => self.complete(context),
+            (_, event::KeyCode::Char(char)) => {
+                self.input.push(*char);
+                self.edited(context);
+            }
+            (_, event::KeyCode::Backspace) => {
+                self.input.pop();
+                self.edited(context);
+            }
+            _ => {}
+        }
+
+        Ok(HandleInput::Consumed)
+    }
+}
+
+fn set_filter(context: &mut Context, text: String) {
+    context.series_filter = (!text.is_empty()).then(|| SeriesFilter::new(text));
+}
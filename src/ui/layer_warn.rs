@@ -3,10 +3,11 @@ use std::time::SystemTime;
 
 use anyhow::Result;
 use arcstr::ArcStr;
-use crossterm::event::{self, Event, KeyEvent};
+use crossterm::event::Event;
 use ratatui::style::{Style, Stylize as _};
 use ratatui::{layout, text, widgets};
 
+use super::keymap::{Action, Scope};
 use super::{Context, HandleInput, LayerCommand, LayerTrait};
 use crate::util::{center_subrect, rect_resize, Gravity};
 
@@ -52,6 +53,7 @@ impl LayerTrait for LayerWarn {
         const DISPLAYED_ITEMS: usize = 16;
 
         let visible = self.is_visible(context);
+        let theme = &context.theme;
 
         if !visible.is_empty() {
             let src = self.warnings_src(context);
@@ -70,7 +72,7 @@ impl LayerTrait for LayerWarn {
                                         chrono::DateTime::<chrono::offset::Local>::from(time)
                                             .format("%H:%M:%S%.3f")
                                             .to_string(),
-                                        Style::default().cyan(),
+                                        Style::default().fg(theme.highlight),
                                     )
                                 } else {
                                     text::Span::raw(std::str::from_utf8(&[b' '; 12]).unwrap())
@@ -87,12 +89,12 @@ impl LayerTrait for LayerWarn {
                 text.push(text::Line::styled("No warnings", Style::default().dim()));
             }
 
-            let mut border_style = Style::default().yellow();
+            let mut border_style = Style::default().fg(theme.warning_border);
             if visible.contains(VisibleReason::RECENT_WARNING) {
                 border_style = border_style.rapid_blink();
             }
             if visible.contains(VisibleReason::FOCUSED) {
-                border_style = border_style.on_black();
+                border_style = border_style.bg(theme.focus);
             }
 
             let mut title = vec![text::Span::raw("Warnings")];
@@ -103,11 +105,11 @@ impl LayerTrait for LayerWarn {
             if self.offset > 0 {
                 title.push(text::Span::styled(
                     format!(" [{scroll_pos}/{scroll_size}]"),
-                    Style::default().light_blue(),
+                    Style::default().fg(theme.highlight),
                 ));
             }
             if self.freeze.is_some() {
-                title.push(text::Span::styled(" [FROZEN]", Style::default().red()));
+                title.push(text::Span::styled(" [FROZEN]", Style::default().fg(theme.alert)));
             }
 
             let rect = if self.zoomed {
@@ -146,19 +148,19 @@ impl LayerTrait for LayerWarn {
         _layer_cmds: &mut Vec<LayerCommand>,
         _frame_size: layout::Rect,
     ) -> Result<HandleInput> {
-        Ok(match event {
-            Event::Key(KeyEvent { code: event::KeyCode::Char('w'), .. }) => {
+        Ok(match context.keymap.action(Scope::Warnings, event) {
+            Some(Action::FocusWarnings) => {
                 self.focused = !self.focused;
                 self.zoomed = false;
                 self.freeze = None;
 
                 HandleInput::Consumed
             }
-            &Event::Key(KeyEvent { code: event::KeyCode::Char('z'), .. }) if self.focused => {
+            Some(Action::ZoomWarnings) if self.focused => {
                 self.zoomed = !self.zoomed;
                 HandleInput::Consumed
             }
-            &Event::Key(KeyEvent { code: event::KeyCode::Char(' '), .. }) if self.focused => {
+            Some(Action::FreezeWarnings) if self.focused => {
                 if self.freeze.is_some() {
                     self.freeze = None;
                 } else {
@@ -166,16 +168,18 @@ impl LayerTrait for LayerWarn {
                 }
                 HandleInput::Consumed
             }
-            &Event::Key(KeyEvent {
-                code: event::KeyCode::Char(key @ ('j' | 'k' | 'g' | 'G')),
-                ..
-            }) if self.focused => {
+            Some(
+                action @ (Action::ScrollDown
+                | Action::ScrollUp
+                | Action::ScrollOldest
+                | Action::ScrollLatest),
+            ) if self.focused => {
                 let max_offset = self.warnings_src(context).len().saturating_sub(1);
-                self.offset = match key {
-                    'j' => self.offset.saturating_sub(1),
-                    'k' => self.offset.saturating_add(1).min(max_offset),
-                    'g' => max_offset,
-                    'G' => 0,
+                self.offset = match action {
+                    Action::ScrollDown => self.offset.saturating_sub(1),
+                    Action::ScrollUp => self.offset.saturating_add(1).min(max_offset),
+                    Action::ScrollOldest => max_offset,
+                    Action::ScrollLatest => 0,
                     _ => unreachable!(),
                 };
                 HandleInput::Consumed
@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{Context as _, Result};
+use serde::{Deserialize, Serialize};
+
+use super::data::{Axis, DrawStyle};
+use super::group::{Aggregate, GroupPath};
+use super::layer_legend::LegendSort;
+
+/// Interactive UI state persisted across runs with `--session`.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Session {
+    /// Display settings of each series by label.
+    pub series:    BTreeMap<String, SeriesState>,
+    pub legend:    Option<LegendState>,
+    pub chart:     Option<ChartState>,
+    pub transform: Option<TransformState>,
+    /// The series used as the x axis in X-Y mode.
+    pub x_series:  Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SeriesState {
+    #[serde(with = "hex_color")]
+    pub color:   [u8; 3],
+    pub visible: bool,
+    pub style:   DrawStyle,
+    pub axis:    Axis,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[allow(clippy::struct_excessive_bools)] // session file fields
+pub struct LegendState {
+    pub anchor_right:  bool,
+    pub anchor_bottom: bool,
+    pub x_displace:    u16,
+    pub y_displace:    u16,
+    #[serde(default)]
+    pub sort:          LegendSort,
+    #[serde(default)]
+    pub compact:       bool,
+    #[serde(default)]
+    pub show_empty:    bool,
+    /// The legend search entered with `/`.
+    #[serde(default)]
+    pub filter:        Option<String>,
+    #[serde(default)]
+    pub collapsed:     Vec<GroupPath>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ChartState {
+    /// Seconds before now at the left edge of the time window.
+    pub x_start: f64,
+    /// Seconds before now at the right edge of the time window.
+    pub x_end:   f64,
+    pub heatmap: bool,
+    #[serde(default)]
+    pub y_log:   bool,
+}
+
+/// How series are grouped and aggregated, and the settings of the distribution view.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct TransformState {
+    pub group_separator: Option<String>,
+    pub group_by:        Vec<String>,
+    /// Collapsed groups plotted as a single aggregate line.
+    pub aggregates:      Vec<AggregateState>,
+    pub histogram_bins:  Option<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct AggregateState {
+    pub group:     GroupPath,
+    pub aggregate: Aggregate,
+}
+
+impl Session {
+    /// Loads the session from `path`, or returns an empty session if the file does not exist.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err).context("read session file"),
+        };
+        toml::from_str(&content).context("parse session file")
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = toml::to_string(self).context("serialize session")?;
+        std::fs::write(path, content).context("write session file")
+    }
+}
+
+mod hex_color {
+    use serde::{de, Deserialize as _};
+
+    use crate::util;
+
+    #[allow(clippy::trivially_copy_pass_by_ref)] // signature required by serde
+    pub fn serialize<S: serde::Serializer>(color: &[u8; 3], s: S) -> Result<S::Ok, S::Error> {
+        let [r, g, b] = color;
+        s.serialize_str(&format!("#{r:02x}{g:02x}{b:02x}"))
+    }
+
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(d: D) -> Result<[u8; 3], D::Error> {
+        let color = String::deserialize(d)?;
+        util::parse_color(&color).map_err(de::Error::custom)
+    }
+}
@@ -0,0 +1,113 @@
+use std::env;
+
+use plotters::style::RGBColor;
+use ratatui::style::Color;
+
+/// A built-in theme selected with `--theme`.
+#[derive(Debug, Clone, Copy, Default, clap::ValueEnum)]
+pub enum ThemeName {
+    /// Detect from the `COLORFGBG` environment variable, falling back to `dark`.
+    #[default]
+    Auto,
+    /// For terminals with a dark background.
+    Dark,
+    /// For terminals with a light background.
+    Light,
+    /// Saturated colors on a dark background.
+    HighContrast,
+}
+
+/// Colors of the UI elements other than the series themselves.
+pub struct Theme {
+    /// Background of exported charts; the terminal background is used in the UI.
+    pub background:     Color,
+    /// Axes, tick labels and other chart text.
+    pub axis:           Color,
+    /// Annotation markers and percentile lines.
+    pub marker:         Color,
+    /// Shaded gap regions.
+    pub gap:            Color,
+    /// Border background of the focused layer.
+    pub focus:          Color,
+    /// Border of the warnings layer.
+    pub warning_border: Color,
+    /// Keys in the help screen and timestamps in the warnings layer.
+    pub highlight:      Color,
+    /// Indicators of unusual states, e.g. frozen warnings.
+    pub alert:          Color,
+}
+
+impl Theme {
+    pub fn new(name: ThemeName) -> Self {
+        match name {
+            ThemeName::Auto => Self::new(detect()),
+            ThemeName::Dark => Self {
+                background:     Color::Black,
+                axis:           Color::White,
+                marker:         Color::Rgb(192, 192, 192),
+                gap:            Color::Rgb(64, 64, 64),
+                focus:          Color::Black,
+                warning_border: Color::Yellow,
+                highlight:      Color::Cyan,
+                alert:          Color::LightRed,
+            },
+            ThemeName::Light => Self {
+                background:     Color::Rgb(255, 255, 255),
+                axis:           Color::Rgb(0, 0, 0),
+                marker:         Color::Rgb(96, 96, 96),
+                gap:            Color::Rgb(216, 216, 216),
+                focus:          Color::Rgb(224, 224, 224),
+                warning_border: Color::Rgb(176, 112, 0),
+                highlight:      Color::Rgb(0, 110, 160),
+                alert:          Color::Rgb(200, 0, 0),
+            },
+            ThemeName::HighContrast => Self {
+                background:     Color::Rgb(0, 0, 0),
+                axis:           Color::Rgb(255, 255, 255),
+                marker:         Color::Rgb(255, 255, 255),
+                gap:            Color::Rgb(96, 96, 96),
+                focus:          Color::Rgb(0, 0, 160),
+                warning_border: Color::Rgb(255, 255, 0),
+                highlight:      Color::Rgb(0, 255, 255),
+                alert:          Color::Rgb(255, 0, 0),
+            },
+        }
+    }
+}
+
+/// Converts a theme color for drawing with plotters, which only supports RGB colors.
+///
+/// Named colors use the common xterm values, so `White` matches plotters' `WHITE`.
+pub fn plot(color: Color) -> RGBColor {
+    let (r, g, b) = match color {
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Black | Color::Reset => (0, 0, 0),
+        Color::Red => (205, 0, 0),
+        Color::Green => (0, 205, 0),
+        Color::Yellow => (205, 205, 0),
+        Color::Blue => (0, 0, 238),
+        Color::Magenta => (205, 0, 205),
+        Color::Cyan => (0, 205, 205),
+        Color::Gray => (229, 229, 229),
+        Color::DarkGray => (127, 127, 127),
+        Color::LightRed => (255, 0, 0),
+        Color::LightGreen => (0, 255, 0),
+        Color::LightYellow => (255, 255, 0),
+        Color::LightBlue => (92, 92, 255),
+        Color::LightMagenta => (255, 0, 255),
+        Color::LightCyan => (0, 255, 255),
+        Color::White | Color::Indexed(_) => (255, 255, 255),
+    };
+    RGBColor(r, g, b)
+}
+
+/// Guesses whether the terminal has a light background from `COLORFGBG`,
+/// which is set by some terminals in the form `FOREGROUND;BACKGROUND`.
+fn detect() -> ThemeName {
+    let Ok(colorfgbg) = env::var("COLORFGBG") else { return ThemeName::Dark };
+    match colorfgbg.rsplit(';').next().and_then(|bg| bg.parse::<u8>().ok()) {
+        // white, light grey and bright colors other than bright black
+        Some(7 | 9..=15) => ThemeName::Light,
+        _ => ThemeName::Dark,
+    }
+}
@@ -1,4 +1,7 @@
-use anyhow::Result;
+use std::fs::Metadata;
+use std::time::Duration;
+
+use anyhow::{Context as _, Result};
 use futures::future::pending;
 use futures::{Stream, StreamExt as _};
 use num_traits::{SaturatingAdd, SaturatingSub};
@@ -14,6 +17,19 @@ impl<T, F: FnOnce(T) -> Result<()>> Drop for Finally<T, F> {
     }
 }
 
+/// Whether both metadata are of the same file, rather than of a file replaced at the same path.
+#[cfg(unix)]
+#[must_use]
+pub fn same_file(a: &Metadata, b: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt as _;
+    (a.dev(), a.ino()) == (b.dev(), b.ino())
+}
+
+/// Replacement cannot be detected without inode numbers.
+#[cfg(not(unix))]
+#[must_use]
+pub fn same_file(_: &Metadata, _: &Metadata) -> bool { true }
+
 pub async fn some_or_pending<T>(option: &mut Option<impl Stream<Item = T> + Unpin>) -> T {
     if let Some(stream) = option {
         let item = stream.next().await;
@@ -28,6 +44,74 @@ pub async fn some_or_pending<T>(option: &mut Option<impl Stream<Item = T> + Unpi
     }
 }
 
+/// Matches `text` against a wildcard pattern,
+/// where `*` matches any substring and `?` matches any single character.
+#[must_use]
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut pattern_pos, mut text_pos) = (0, 0);
+    let mut last_star = None;
+    while text_pos < text.len() {
+        match pattern.get(pattern_pos) {
+            Some('*') => {
+                last_star = Some((pattern_pos, text_pos));
+                pattern_pos += 1;
+            }
+            Some(&ch) if ch == '?' || ch == text[text_pos] => {
+                pattern_pos += 1;
+                text_pos += 1;
+            }
+            _ => {
+                // let the last `*` consume one more character
+                let Some((star_pattern_pos, star_text_pos)) = last_star else { return false };
+                pattern_pos = star_pattern_pos + 1;
+                text_pos = star_text_pos + 1;
+                last_star = Some((star_pattern_pos, text_pos));
+            }
+        }
+    }
+
+    pattern[pattern_pos..].iter().all(|&ch| ch == '*')
+}
+
+/// Parses a color in the form `#rrggbb`.
+///
+/// # Errors
+/// Returns an error if `color` is not a valid hex color.
+pub fn parse_color(color: &str) -> Result<[u8; 3]> {
+    let hex = color.strip_prefix('#').unwrap_or(color);
+    anyhow::ensure!(
+        hex.len() == 6 && hex.is_ascii(),
+        "color {color:?} should be in the form `#rrggbb`"
+    );
+    let mut rgb = [0; 3];
+    for (i, channel) in rgb.iter_mut().enumerate() {
+        *channel = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map_err(|err| anyhow::anyhow!("invalid color {color:?}: {err}"))?;
+    }
+    Ok(rgb)
+}
+
+/// Parses a duration in seconds, or with an `s`, `m` or `h` suffix.
+///
+/// # Errors
+/// Returns an error if `duration` is not a non-negative number with an optional unit.
+pub fn parse_duration(duration: &str) -> Result<Duration> {
+    let (number, unit) = match duration.char_indices().last() {
+        Some((index, unit @ ('s' | 'm' | 'h'))) => (&duration[..index], unit),
+        _ => (duration, 's'),
+    };
+    let number: f64 = number.parse().with_context(|| format!("invalid duration {duration:?}"))?;
+    let secs = match unit {
+        'm' => number * 60.,
+        'h' => number * 3600.,
+        _ => number,
+    };
+    Duration::try_from_secs_f64(secs).with_context(|| format!("invalid duration {duration:?}"))
+}
+
 #[must_use]
 pub fn disp_float(value: f64, digits: u32) -> String {
     if value == 0.0 {
@@ -48,8 +132,8 @@ pub fn disp_float(value: f64, digits: u32) -> String {
 
 #[must_use]
 pub fn center_subrect(rect: layout::Rect, ratio: (u16, u16)) -> layout::Rect {
-    let center_x = (rect.left() + rect.right()) / 2;
-    let center_y = (rect.top() + rect.bottom()) / 2;
+    let center_x = u16::midpoint(rect.left(), rect.right());
+    let center_y = u16::midpoint(rect.top(), rect.bottom());
     let new_width = rect.width * ratio.0 / ratio.1;
     let new_height = rect.height * ratio.0 / ratio.1;
 
@@ -218,3 +302,49 @@ pub trait SaturatingSubExt {
 impl<T: SaturatingSub> SaturatingSubExt for T {
     fn saturating_sub_assign(&mut self, other: Self) { *self = self.saturating_sub(&other); }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{glob_match, parse_color, parse_duration};
+
+    #[test]
+    fn glob_match_wildcards() {
+        assert!(glob_match("cpu*", "cpu0"));
+        assert!(glob_match("cpu*", "cpu"));
+        assert!(glob_match("*.user", "node1.cpu.user"));
+        assert!(glob_match("c?u", "cpu"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("é?", "éa"));
+
+        assert!(!glob_match("cpu", "cpu0"));
+        assert!(!glob_match("c?u", "cu"));
+        assert!(!glob_match("*.user", "node1.cpu.system"));
+        assert!(!glob_match("", "a"));
+    }
+
+    #[test]
+    fn parse_duration_units() {
+        assert_eq!(parse_duration("30").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("10s").unwrap(), Duration::from_secs(10));
+        assert_eq!(parse_duration("1.5m").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_hours(2));
+
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("-1").is_err());
+        assert!(parse_duration("5d").is_err());
+    }
+
+    #[test]
+    fn parse_color_hex() {
+        assert_eq!(parse_color("#ff8000").unwrap(), [255, 128, 0]);
+        assert_eq!(parse_color("00FF0a").unwrap(), [0, 255, 10]);
+
+        assert!(parse_color("#fff").is_err());
+        assert!(parse_color("#gg0000").is_err());
+        assert!(parse_color("#ééé").is_err());
+    }
+}